@@ -0,0 +1,17 @@
+//! Settings shared by the benchmarks.
+
+use std::env;
+
+/// Seed of all generated workloads
+pub const SEED: u64 = 42;
+
+/// Numbers of entries the benchmarks run at
+const SIZES: [usize; 5] = [1_000, 10_000, 100_000, 1_000_000, 10_000_000];
+
+/// Returns the sizes which should be benchmarked, those above `BENCH_MAX_SIZE` are skipped.
+pub fn sizes() -> Vec<usize> {
+    let max = env::var("BENCH_MAX_SIZE").ok()
+        .map(|max| max.parse().expect("BENCH_MAX_SIZE must be a number"))
+        .unwrap_or(usize::MAX);
+    SIZES.iter().cloned().filter(|&n| n <= max).collect()
+}
@@ -0,0 +1,68 @@
+//! Memory use per element of `Tree` and `BTreeSet<(i32, String)>`.
+//!
+//! Run with `cargo bench --bench memory`. A counting allocator measures the bytes which are
+//! allocated for a collection built by inserting random keys, including the names.
+//! The bytes requested from the allocator are counted, without its own overhead.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustsint::workload;
+use rustsint::Tree;
+
+mod common;
+
+use common::{sizes, SEED};
+
+/// The system allocator, counting the bytes which are currently allocated
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Returns the bytes allocated per key by the collection which `build` returns.
+fn bytes_per_key<T, F: FnOnce() -> T>(n: usize, build: F) -> f64 {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let collection = build();
+    let bytes = ALLOCATED.load(Ordering::Relaxed) - before;
+    drop(collection);
+    bytes as f64 / n as f64
+}
+
+fn main() {
+    // Ignore the arguments which cargo bench passes to every benchmark
+    println!("{:>10} {:>16} {:>16}", "entries", "Tree B/entry", "BTreeSet B/entry");
+    for n in sizes() {
+        let keys = workload::random(n, SEED);
+        let tree = bytes_per_key(n, || {
+            let mut tree = Tree::new();
+            for (age, name) in &keys {
+                tree.insert(*age, name.clone());
+            }
+            tree
+        });
+        let set = bytes_per_key(n, || keys.iter().cloned().collect::<BTreeSet<_>>());
+        println!("{:>10} {:>16.1} {:>16.1}", n, tree, set);
+    }
+}
@@ -0,0 +1,189 @@
+//! Benchmarks of `Tree` against `BTreeSet<(i32, String)>`, which is a `BTreeMap<(i32, String), ()>`.
+//!
+//! Run with `cargo bench --bench tree`. Every operation is measured at 1e3 to 1e7 entries,
+//! set `BENCH_MAX_SIZE` to skip the larger sizes. Throughput is reported per operation.
+
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
+use std::time::Duration;
+
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion,
+                SamplingMode, Throughput};
+use rustsint::workload::{self, Op};
+use rustsint::Tree;
+
+mod common;
+
+use common::{sizes, SEED};
+
+/// Number of lookups per iteration of the find benchmarks
+const LOOKUPS: usize = 10_000;
+
+/// Returns a group which takes few samples, as an iteration at the largest sizes takes seconds.
+fn group<'a>(c: &'a mut Criterion, name: &str) -> BenchmarkGroup<'a, WallTime> {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10).sampling_mode(SamplingMode::Flat).measurement_time(Duration::from_secs(10));
+    group
+}
+
+/// Returns the keys sorted, as `Tree::from_sorted` expects them.
+fn sorted(keys: &[(i32, String)]) -> Vec<(i32, String)> {
+    let mut sorted = keys.to_vec();
+    sorted.sort();
+    sorted
+}
+
+/// Apply an operation of a mixed workload to the set.
+fn apply(set: &mut BTreeSet<(i32, String)>, op: &Op) -> bool {
+    match op {
+        Op::Insert(age, name) => set.insert((*age, name.clone())),
+        Op::Erase(age, name) => set.remove(&(*age, name.clone())),
+        Op::Find(age, name) => set.contains(&(*age, name.clone())),
+    }
+}
+
+/// Insert the keys one by one into an empty tree and set.
+fn bench_insert(group: &mut BenchmarkGroup<WallTime>, keys: &[(i32, String)]) {
+    let n = keys.len();
+    group.throughput(Throughput::Elements(n as u64));
+    group.bench_with_input(BenchmarkId::new("Tree", n), keys, |b, keys| {
+        b.iter_with_large_drop(|| {
+            let mut tree = Tree::new();
+            for (age, name) in keys {
+                tree.insert(*age, name.clone());
+            }
+            tree
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("BTreeSet", n), keys, |b, keys| {
+        b.iter_with_large_drop(|| {
+            let mut set = BTreeSet::new();
+            for (age, name) in keys {
+                set.insert((*age, name.clone()));
+            }
+            set
+        })
+    });
+}
+
+fn insert_sequential(c: &mut Criterion) {
+    let mut group = group(c, "insert_sequential");
+    for n in sizes() {
+        bench_insert(&mut group, &workload::sequential(n));
+    }
+    group.finish();
+}
+
+fn insert_random(c: &mut Criterion) {
+    let mut group = group(c, "insert_random");
+    for n in sizes() {
+        bench_insert(&mut group, &workload::random(n, SEED));
+    }
+    group.finish();
+}
+
+/// Look up each of the keys in a tree and set holding `n` random keys.
+fn bench_find(group: &mut BenchmarkGroup<WallTime>, n: usize, lookups: &[(i32, String)]) {
+    let sorted = sorted(&workload::random(n, SEED));
+    let tree = Tree::from_sorted(sorted.clone());
+    let set = BTreeSet::from_iter(sorted);
+    group.throughput(Throughput::Elements(lookups.len() as u64));
+    group.bench_with_input(BenchmarkId::new("Tree", n), lookups, |b, lookups| {
+        b.iter(|| lookups.iter().filter(|(age, name)| tree.find(*age, name.clone())).count())
+    });
+    group.bench_with_input(BenchmarkId::new("BTreeSet", n), lookups, |b, lookups| {
+        b.iter(|| lookups.iter().filter(|(age, name)| set.contains(&(*age, name.clone()))).count())
+    });
+}
+
+fn find_hit(c: &mut Criterion) {
+    let mut group = group(c, "find_hit");
+    for n in sizes() {
+        let mut lookups = workload::random(n, SEED);
+        lookups.truncate(LOOKUPS);
+        bench_find(&mut group, n, &lookups);
+    }
+    group.finish();
+}
+
+fn find_miss(c: &mut Criterion) {
+    let mut group = group(c, "find_miss");
+    for n in sizes() {
+        bench_find(&mut group, n, &workload::misses(LOOKUPS, SEED));
+    }
+    group.finish();
+}
+
+fn erase(c: &mut Criterion) {
+    let mut group = group(c, "erase");
+    for n in sizes() {
+        let keys = workload::random(n, SEED);
+        let sorted = sorted(&keys);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("Tree", n), &keys, |b, keys| {
+            b.iter_batched(|| Tree::from_sorted(sorted.clone()), |mut tree| {
+                for (age, name) in keys {
+                    tree.erase(*age, name.clone());
+                }
+                tree
+            }, BatchSize::PerIteration)
+        });
+        group.bench_with_input(BenchmarkId::new("BTreeSet", n), &keys, |b, keys| {
+            b.iter_batched(|| BTreeSet::from_iter(sorted.clone()), |mut set| {
+                for (age, name) in keys {
+                    set.remove(&(*age, name.clone()));
+                }
+                set
+            }, BatchSize::PerIteration)
+        });
+    }
+    group.finish();
+}
+
+/// `n` operations on `n` keys, half of which are in the tree at the start
+fn mixed(c: &mut Criterion) {
+    let mut group = group(c, "mixed");
+    for n in sizes() {
+        let keys = workload::random(n, SEED);
+        let initial = sorted(&keys[..n / 2]);
+        let ops = workload::mixed(&keys, n, SEED);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("Tree", n), &ops, |b, ops| {
+            b.iter_batched(|| Tree::from_sorted(initial.clone()), |mut tree| {
+                for op in ops {
+                    black_box(op.apply(&mut tree));
+                }
+                tree
+            }, BatchSize::PerIteration)
+        });
+        group.bench_with_input(BenchmarkId::new("BTreeSet", n), &ops, |b, ops| {
+            b.iter_batched(|| BTreeSet::from_iter(initial.clone()), |mut set| {
+                for op in ops {
+                    black_box(apply(&mut set, op));
+                }
+                set
+            }, BatchSize::PerIteration)
+        });
+    }
+    group.finish();
+}
+
+/// Build from sorted entries, with `Tree::from_sorted` and the bulk load of `BTreeSet`
+fn construction(c: &mut Criterion) {
+    let mut group = group(c, "construction");
+    for n in sizes() {
+        let sorted = sorted(&workload::random(n, SEED));
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("Tree", n), &sorted, |b, sorted| {
+            b.iter_batched(|| sorted.clone(), Tree::from_sorted, BatchSize::PerIteration)
+        });
+        group.bench_with_input(BenchmarkId::new("BTreeSet", n), &sorted, |b, sorted| {
+            b.iter_batched(|| sorted.clone(), BTreeSet::from_iter, BatchSize::PerIteration)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, insert_sequential, insert_random, find_hit, find_miss, erase, mixed, construction);
+criterion_main!(benches);
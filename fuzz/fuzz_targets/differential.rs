@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use rustsint::differential::{run, Op};
+
+fuzz_target!(|ops: Vec<Op>| {
+    if let Err(error) = run(&ops) {
+        panic!("{}", error);
+    }
+});
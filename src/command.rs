@@ -0,0 +1,1195 @@
+use std::collections::btree_map::Entry;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::mem;
+use std::str::FromStr;
+
+use crate::history::{Change, History, Record, DEFAULT_HISTORY_DEPTH};
+use crate::lexer::{self, Token};
+use crate::trace::Tracer;
+use crate::tree::{AsciiOptions, SnapshotError, Tree};
+use crate::wal::{Mutation, Wal, WalOptions};
+
+#[derive(Debug)]
+enum Command {
+    Insert{age: i32, name: String},
+    Erase{age: i32, name: String},
+    Contains{age: i32, name: String},
+    Print,
+    Dot,
+    Ascii{details: bool},
+    Reset,
+    Save{path: String},
+    Load{path: String},
+    Stats,
+    Trace{on: bool},
+    Size,
+    Height,
+    Min,
+    Max,
+    Rank{age: i32, name: String},
+    Select{k: usize},
+    Successor{age: i32, name: String},
+    Predecessor{age: i32, name: String},
+    Use{tree: String},
+    New{tree: String},
+    Drop{tree: String},
+    List,
+    Union{other: String},
+    Intersection{other: String},
+    Difference{other: String},
+    Equal{other: String},
+    Undo,
+    Redo,
+    History,
+    Begin,
+    Commit,
+    Rollback,
+    Exit,
+    Error(String)
+}
+
+/// Parse a line into the tree it targets and its command.
+/// Returns the name given with `@<tree>`, or None for the tree in use, and the command.
+fn parse_line(input: &str) -> (Option<String>, Command) {
+    let tokens = match lexer::tokenize(input) {
+        Ok(tokens) => tokens,
+        Err(error) => return (None, Command::Error(error.to_string()))
+    };
+    let (target, tokens) = match tokens.first() {
+        Some(token) if !token.quoted && token.text.starts_with('@') => (Some(&token.text[1..]), &tokens[1..]),
+        _ => (None, &tokens[..])
+    };
+    let target = match target {
+        Some("") => return (None, Command::Error("column 1: expected @<tree> <command>".to_string())),
+        Some(target) => target,
+        None => return (None, parse_command(input, tokens))
+    };
+    let command = match tokens.first().map(|token| token.text.as_str()) {
+        None => Command::Error(format!("column {}: expected a command after @{}", input.trim_end().chars().count() + 1, target)),
+        Some(name @ ("use" | "new" | "drop" | "list" | "u" | "r" | "history" | "begin" | "commit" | "rollback" | "t" | "q")) => {
+            Command::Error(format!("column {}: {} cannot be used with @<tree>", tokens[0].column, name))
+        }
+        Some(_) => parse_command(input, tokens)
+    };
+    (Some(target.to_string()), command)
+}
+
+/// Parse the words of a line into a command.
+/// Returns the command, or an error command if the words are not a valid command.
+///
+/// # Arguments
+///
+/// * `input` - The line
+/// * `tokens` - The words of the command, after the `@<tree>` if there is one
+fn parse_command(input: &str, tokens: &[Token]) -> Command {
+    let command_items: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+    if command_items.is_empty() {
+        Command::Error("invalid command (empty line)".to_string())
+    } else {
+        match (command_items[0], command_items.len()) {
+            ("p", 1) => Command::Print,
+            ("d", 1) => Command::Dot,
+            ("a", 1) => Command::Ascii{details: false},
+            ("a", 2) if command_items[1] == "v" => Command::Ascii{details: true},
+            ("q", 1) => Command::Exit,
+            ("x", 1) => Command::Reset,
+            ("stats", 1) => Command::Stats,
+            ("t", 2) if command_items[1] == "on" => Command::Trace{on: true},
+            ("t", 2) if command_items[1] == "off" => Command::Trace{on: false},
+            ("save", 2) => Command::Save{path: command_items[1].to_string()},
+            ("load", 2) => Command::Load{path: command_items[1].to_string()},
+            ("s", 1) => Command::Size,
+            ("h", 1) => Command::Height,
+            ("min", 1) => Command::Min,
+            ("max", 1) => Command::Max,
+            ("sel", 2) => {
+                if let Ok(k) = command_items[1].parse::<usize>() {
+                    Command::Select{k}
+                } else {
+                    Command::Error(format!("column {}: unable to parse int (k).", tokens[1].column))
+                }
+            },
+            ("i", 3) => parse_key(tokens, |age, name| Command::Insert{age, name}),
+            ("e", 3) => parse_key(tokens, |age, name| Command::Erase{age, name}),
+            ("c", 3) => parse_key(tokens, |age, name| Command::Contains{age, name}),
+            ("rank", 3) => parse_key(tokens, |age, name| Command::Rank{age, name}),
+            ("succ", 3) => parse_key(tokens, |age, name| Command::Successor{age, name}),
+            ("pred", 3) => parse_key(tokens, |age, name| Command::Predecessor{age, name}),
+            ("use", 2) => Command::Use{tree: command_items[1].to_string()},
+            ("new", 2) => Command::New{tree: command_items[1].to_string()},
+            ("drop", 2) => Command::Drop{tree: command_items[1].to_string()},
+            ("list", 1) => Command::List,
+            ("u", 1) => Command::Undo,
+            ("r", 1) => Command::Redo,
+            ("history", 1) => Command::History,
+            ("begin", 1) => Command::Begin,
+            ("commit", 1) => Command::Commit,
+            ("rollback", 1) => Command::Rollback,
+            ("union", 2) => Command::Union{other: command_items[1].to_string()},
+            ("inter", 2) => Command::Intersection{other: command_items[1].to_string()},
+            ("diff", 2) => Command::Difference{other: command_items[1].to_string()},
+            ("eq", 2) => Command::Equal{other: command_items[1].to_string()},
+
+            (_, _) => usage_error(input, tokens)
+        }
+    }
+}
+
+/// Parse the `<age> <name>` arguments of a command.
+/// Returns the command built from them, or an error command if the age is not a number.
+///
+/// # Arguments
+///
+/// * `tokens` - The command and its two arguments
+/// * `command` - Builds the command from the age and name
+fn parse_key(tokens: &[Token], command: impl FnOnce(i32, String) -> Command) -> Command {
+    if let Ok(age) = tokens[1].text.parse::<i32>() {
+        command(age, tokens[2].text.clone())
+    } else {
+        Command::Error(format!("column {}: unable to parse int (age).", tokens[1].column))
+    }
+}
+
+/// Returns the arguments which the given command expects, or None if there is no such command.
+fn arguments(command: &str) -> Option<&'static [&'static str]> {
+    match command {
+        "p" | "d" | "q" | "x" | "stats" | "s" | "h" | "min" | "max" | "list" | "u" | "r" | "history" | "begin" | "commit" | "rollback" => Some(&[]),
+        "a" => Some(&["[v]"]),
+        "t" => Some(&["on|off"]),
+        "save" | "load" => Some(&["<path>"]),
+        "sel" => Some(&["<k>"]),
+        "use" | "new" | "drop" | "union" | "inter" | "diff" | "eq" => Some(&["<tree>"]),
+        "i" | "e" | "c" | "rank" | "succ" | "pred" => Some(&["<age>", "<name>"]),
+        _ => None
+    }
+}
+
+/// Returns the error of a line which is not a valid command.
+///
+/// # Arguments
+///
+/// * `input` - The line
+/// * `tokens` - The words of the line, at least one
+///
+/// # Discussion
+///
+/// The column points at the unknown command, the first superfluous or invalid argument,
+/// or the end of the line when an argument is missing.
+fn usage_error(input: &str, tokens: &[Token]) -> Command {
+    let command = &tokens[0];
+    let expected = match arguments(&command.text) {
+        Some(expected) => expected,
+        None => return Command::Error(format!("column {}: invalid command {}.", command.column, lexer::quote(&command.text)))
+    };
+    let column = if tokens.len() <= expected.len() {
+        input.trim_end().chars().count() + 1
+    } else if tokens.len() > expected.len() + 1 {
+        tokens[expected.len() + 1].column
+    } else {
+        // The number of arguments is right, but `a` or `t` got an invalid one
+        tokens[1].column
+    };
+    let mut usage = command.text.clone();
+    for argument in expected {
+        usage.push(' ');
+        usage.push_str(argument);
+    }
+    Command::Error(format!("column {}: expected {}", column, usage))
+}
+
+/// Settings of the command interpreter
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Log every mutation to a write-ahead log and recover the tree from it on startup
+    pub wal: Option<WalOptions>,
+    /// Check the invariants of the tree after every command, a violation is returned as an error
+    pub validate: bool,
+    /// Start in trace mode, which writes every step of `i` and `e` to the output, see `trace`
+    pub trace: bool,
+    /// Stop at the first error and return it, instead of reporting it on stderr and going on
+    pub strict: bool,
+    /// Treat a `c` of an entry which is not in the tree as an error
+    pub check: bool,
+    /// How `p` writes the tree
+    pub format: Format,
+    /// Number of mutations which `u` can undo, 0 disables undo
+    pub history: usize,
+    /// Allow `save` and `load`, which write and read files
+    pub files: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            wal: None,
+            validate: false,
+            trace: false,
+            strict: false,
+            check: false,
+            format: Format::default(),
+            history: DEFAULT_HISTORY_DEPTH,
+            files: true,
+        }
+    }
+}
+
+/// How `p` writes the tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Nested JSON arrays on a single line, see `Tree::write_json`
+    #[default]
+    Json,
+    /// Indented JSON, see `Tree::write_json_pretty`
+    Pretty,
+    /// Graphviz dot, like `d`
+    Dot,
+    /// Box-drawing diagram, like `a`
+    Ascii,
+    /// One `<age> <name>` line per entry in sorted order, with names quoted like the queries do
+    SortedList,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    /// Parse `json`, `pretty`, `dot`, `ascii` or `sorted-list`.
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "json" => Ok(Format::Json),
+            "pretty" => Ok(Format::Pretty),
+            "dot" => Ok(Format::Dot),
+            "ascii" => Ok(Format::Ascii),
+            "sorted-list" => Ok(Format::SortedList),
+            _ => Err(format!("unknown format {}, expected json, pretty, dot, ascii or sorted-list", s))
+        }
+    }
+}
+
+/// An error in a line of the input
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineError {
+    /// Number of the line, the first line is 1
+    pub line: usize,
+    /// The line, without its line break
+    pub text: String,
+    /// Description of the error
+    pub message: String,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: `{}`: {}", self.line, self.text, self.message)
+    }
+}
+
+impl Error for LineError {}
+
+impl LineError {
+    /// Returns the error wrapped in an `io::Error` of the given kind.
+    fn into_io(self, kind: io::ErrorKind) -> io::Error {
+        io::Error::new(kind, self)
+    }
+}
+
+/// Read commands line by line and apply them to a tree until the input ends or `q` is read.
+///
+/// # Arguments
+///
+/// * `br` - Input from which the commands are read
+/// * `out` - Output to which the results of `c` and `p` are written
+///
+/// # Discussion
+///
+/// Invalid commands are reported on stderr and skipped. An error is only returned when writing to `out` fails.
+pub fn command_loop(br: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<()> {
+    command_loop_with(br, out, &Options::default())
+}
+
+/// Read commands line by line and apply them to a tree until the input ends or `q` is read.
+///
+/// # Arguments
+///
+/// * `br` - Input from which the commands are read
+/// * `out` - Output to which the results of `c` and `p` are written
+/// * `options` - Settings of the interpreter
+///
+/// # Discussion
+///
+/// Invalid commands are reported on stderr as a `LineError` and skipped. In strict mode the first
+/// of them is returned instead, as an `io::Error` of kind `InvalidInput` which wraps the `LineError`.
+/// An error is also returned when writing to `out` fails, when the write-ahead log cannot be opened
+/// or written, or when validation finds a broken invariant. Mutations are not applied if logging them failed.
+pub fn command_loop_with(br: &mut dyn BufRead, out: &mut dyn Write, options: &Options) -> io::Result<()> {
+    Interpreter::new(options.clone())?.run(br, out)?;
+    Ok(())
+}
+
+/// Name of the tree a database starts with, which is the only tree kept in the write-ahead log
+pub const DEFAULT_TREE: &str = "default";
+
+/// The committed trees with their write-ahead log and history, which any number of sessions can share
+pub struct Database {
+    /// The committed state
+    state: State,
+    /// Log of the default tree
+    wal: Option<Wal>,
+    options: Options,
+    /// Number of commands which changed the committed state, so a commit can tell whether
+    /// another session changed it since `begin`
+    version: u64,
+}
+
+/// A client of a database: the tree in use, trace mode, line numbers and open transactions
+pub struct Session {
+    /// The state of every open transaction, the innermost last
+    transactions: Vec<State>,
+    /// Version of the database when the outermost open transaction began
+    base: u64,
+    /// Name of the tree which commands without `@<tree>` apply to, see `use`
+    current: String,
+    /// Whether trace mode is on, see `t`
+    trace: bool,
+    /// Number of the line which is executed, counted from the start of the current input
+    line: usize,
+    /// Write errors to the output as `error: ...` lines instead of reporting them on stderr
+    errors_to_output: bool,
+    /// Allow `save` and `load`, if the options of the database allow them as well
+    files: bool,
+    /// Record changes in the history and allow `u`, `r` and `history`
+    history: bool,
+}
+
+/// A database with a single session, which can run several inputs in turn
+pub struct Interpreter {
+    database: Database,
+    session: Session,
+}
+
+/// The trees as seen outside of any transaction, or inside a transaction
+struct State {
+    /// The trees by name, the default tree always exists
+    trees: BTreeMap<String, Tree>,
+    /// Changes which `u` and `r` can undo and redo, a transaction starts with an empty history
+    history: History,
+    /// Mutations of the default tree made in a transaction, which go to the write-ahead log
+    /// when the outermost transaction is committed. Only kept if there is a log.
+    log: Vec<Mutation>,
+}
+
+/// Where the errors of a command go, see `report`
+#[derive(Clone, Copy)]
+struct Reporter<'a> {
+    /// Return the error instead of going on
+    strict: bool,
+    /// Write the error to the output instead of stderr
+    to_output: bool,
+    /// Number of the line with the command
+    line: usize,
+    /// The first error of the command which did not stop it, see `Outcome::Failed`
+    failure: &'a Cell<Option<LineError>>,
+}
+
+/// What became of a command, see `Database::execute`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    /// The command was applied
+    Done,
+    /// The command was `q`
+    Exit,
+    /// The command failed, the error was reported as well, see `Session::report_errors_to_output`
+    Failed(LineError),
+}
+
+impl Database {
+    /// Create a database with an empty default tree, or the tree recovered from the write-ahead log.
+    /// Returns the database, or an error if the log cannot be opened or read.
+    ///
+    /// # Argument
+    ///
+    /// * `options` - Settings of the interpreter
+    pub fn new(options: Options) -> io::Result<Database> {
+        let (wal, tree) = match options.wal {
+            Some(ref wal_options) => {
+                let (wal, tree) = Wal::open(wal_options)?;
+                (Some(wal), tree)
+            }
+            None => (None, Tree::new())
+        };
+        let mut trees = BTreeMap::new();
+        trees.insert(DEFAULT_TREE.to_string(), tree);
+        let state = State { trees, history: History::new(options.history), log: Vec::new() };
+        Ok(Database { state, wal, options, version: 0 })
+    }
+
+    /// Returns a new session, which uses the default tree and reports errors on stderr.
+    pub fn session(&self) -> Session {
+        Session {
+            transactions: Vec::new(),
+            base: self.version,
+            current: DEFAULT_TREE.to_string(),
+            trace: self.options.trace,
+            line: 0,
+            errors_to_output: false,
+            files: true,
+            history: true,
+        }
+    }
+
+    /// Returns the committed tree with the given name, or None if there is no such tree.
+    ///
+    /// # Argument
+    ///
+    /// * `name` - Name of the tree
+    pub fn tree(&self, name: &str) -> Option<&Tree> {
+        self.state.trees.get(name)
+    }
+
+    /// Returns the number of bytes of an incomplete record which were cut off the end of the
+    /// write-ahead log on startup, 0 if there is no log, see `Wal::discarded`.
+    pub fn discarded(&self) -> u64 {
+        self.wal.as_ref().map_or(0, Wal::discarded)
+    }
+
+    /// Write a snapshot of the committed default tree to the file at the given path, replacing the file if it exists.
+    ///
+    /// # Argument
+    ///
+    /// * `path` - Path of the snapshot file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save_snapshot(&self.state.trees[DEFAULT_TREE], path)
+    }
+
+    /// Returns the state which the session reads and changes, the one of its innermost transaction if there is one.
+    ///
+    /// # Argument
+    ///
+    /// * `session` - The session
+    fn view<'a>(&'a self, session: &'a Session) -> &'a State {
+        session.transactions.last().unwrap_or(&self.state)
+    }
+
+    /// Apply a single command of a session.
+    /// Returns what became of the command.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which sent the command
+    /// * `input` - The line with the command, it gets the number after the line executed before
+    /// * `out` - Output to which the result of the command is written
+    ///
+    /// # Discussion
+    ///
+    /// An error ends the session, so all of its open transactions are rolled back when one is returned.
+    pub fn execute(&mut self, session: &mut Session, input: &str, out: &mut dyn Write) -> io::Result<Outcome> {
+        let failure = Cell::new(None);
+        let result = self.execute_command(session, input, out, &failure);
+        if result.is_err() {
+            session.rollback_all(self);
+        }
+        Ok(match (result?, failure.into_inner()) {
+            (false, _) => Outcome::Exit,
+            (true, Some(error)) => Outcome::Failed(error),
+            (true, None) => Outcome::Done
+        })
+    }
+
+    /// Apply a single command, see `execute`.
+    /// Returns false if the command was `q`, true otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which sent the command
+    /// * `input` - The line with the command
+    /// * `out` - Output to which the result of the command is written
+    /// * `failure` - Gets the first error of the command which did not stop it
+    fn execute_command(&mut self, session: &mut Session, input: &str, out: &mut dyn Write,
+                       failure: &Cell<Option<LineError>>) -> io::Result<bool> {
+        session.line += 1;
+        let reporter = Reporter { strict: self.options.strict, to_output: session.errors_to_output, line: session.line, failure };
+        let (target, command) = parse_line(input);
+        if let Command::Error(error) = command {
+            report(reporter, error, input, out)?;
+            return Ok(true);
+        }
+        if !(self.options.files && session.files) && matches!(command, Command::Save{..} | Command::Load{..}) {
+            report(reporter, "save and load are disabled".to_string(), input, out)?;
+            return Ok(true);
+        }
+        // A snapshot on disk cannot be rolled back with the transaction
+        if session.in_transaction() && matches!(command, Command::Save{..}) {
+            report(reporter, "save is not allowed inside a transaction".to_string(), input, out)?;
+            return Ok(true);
+        }
+        if !session.history && matches!(command, Command::Undo | Command::Redo | Command::History) {
+            report(reporter, "undo and redo are disabled".to_string(), input, out)?;
+            return Ok(true);
+        }
+        let name = target.unwrap_or_else(|| session.current.clone());
+        if !self.view(session).trees.contains_key(&name) {
+            report(reporter, format!("no tree named {}", lexer::quote(&name)), input, out)?;
+            return Ok(true);
+        }
+        let committed = !session.in_transaction();
+        if name == DEFAULT_TREE {
+            if let Some(mutation) = command_mutation(&command) {
+                self.log(session, vec![mutation])?;
+            }
+        }
+        let changes = command.changes_trees();
+        match command {
+            Command::Exit => {
+                return Ok(false);
+            },
+            Command::Trace{on} => {
+                session.trace = on;
+            },
+            Command::Use{tree} => {
+                if self.view(session).trees.contains_key(&tree) {
+                    session.current = tree;
+                } else {
+                    report(reporter, format!("no tree named {}", lexer::quote(&tree)), input, out)?;
+                }
+            },
+            Command::New{tree} => {
+                match top(&mut self.state, session).trees.entry(tree) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(Tree::new());
+                    }
+                    Entry::Occupied(entry) => {
+                        report(reporter, format!("tree {} already exists", lexer::quote(entry.key())), input, out)?;
+                    }
+                }
+            },
+            Command::Drop{tree} => {
+                if tree == DEFAULT_TREE {
+                    report(reporter, format!("the {} tree cannot be dropped", DEFAULT_TREE), input, out)?;
+                } else if tree == session.current {
+                    report(reporter, format!("tree {} is in use", lexer::quote(&tree)), input, out)?;
+                } else if top(&mut self.state, session).trees.remove(&tree).is_some() {
+                    top(&mut self.state, session).history.forget(&tree);
+                } else {
+                    report(reporter, format!("no tree named {}", lexer::quote(&tree)), input, out)?;
+                }
+            },
+            Command::List => {
+                for (tree_name, tree) in &self.view(session).trees {
+                    let marker = if *tree_name == session.current { '*' } else { ' ' };
+                    writeln!(out, "{} {} {}", marker, lexer::quote(tree_name), tree.len())?;
+                }
+            },
+            Command::Equal{other} => {
+                let trees = &self.view(session).trees;
+                match trees.get(&other) {
+                    Some(other) => {
+                        let equal = trees[&name].same_entries(other);
+                        writeln!(out, "{}", if equal { "y" } else { "n" })?;
+                    }
+                    None => report(reporter, format!("no tree named {}", lexer::quote(&other)), input, out)?
+                }
+            },
+            Command::Union{ref other} | Command::Intersection{ref other} | Command::Difference{ref other} => {
+                let trees = &self.view(session).trees;
+                match trees.get(other) {
+                    Some(other_tree) => {
+                        let tree = &trees[&name];
+                        let (combined, operation) = match command {
+                            Command::Union{..} => (tree.union(other_tree), "union"),
+                            Command::Intersection{..} => (tree.intersection(other_tree), "inter"),
+                            _ => (tree.difference(other_tree), "diff")
+                        };
+                        let command = format!("{} {}", operation, lexer::quote(other));
+                        self.replace(session, &name, combined, command)?;
+                    }
+                    None => report(reporter, format!("no tree named {}", lexer::quote(other)), input, out)?
+                }
+            },
+            Command::Load{path} => {
+                match load_snapshot(&path) {
+                    Ok(loaded) => self.replace(session, &name, loaded, format!("load {}", lexer::quote(&path)))?,
+                    Err(error) => report(reporter, format!("unable to load {}: {}", path, error), input, out)?
+                }
+            },
+            Command::Undo => {
+                match top(&mut self.state, session).history.undo() {
+                    Some(record) => {
+                        let record = self.make(session, record)?;
+                        top(&mut self.state, session).history.undone(record);
+                    }
+                    None => report(reporter, "nothing to undo".to_string(), input, out)?
+                }
+            },
+            Command::Redo => {
+                match top(&mut self.state, session).history.redo() {
+                    Some(record) => {
+                        let record = self.make(session, record)?;
+                        top(&mut self.state, session).history.redone(record);
+                    }
+                    None => report(reporter, "nothing to redo".to_string(), input, out)?
+                }
+            },
+            Command::History => {
+                for record in self.view(session).history.records() {
+                    writeln!(out, "{}", record.command())?;
+                }
+            },
+            Command::Begin => {
+                if committed {
+                    session.base = self.version;
+                }
+                let trees = self.view(session).trees.clone();
+                session.transactions.push(State { trees, history: History::new(self.options.history), log: Vec::new() });
+            },
+            Command::Commit => {
+                if committed {
+                    report(reporter, "no transaction to commit".to_string(), input, out)?;
+                } else if session.transactions.len() == 1 && session.base != self.version {
+                    session.rollback_all(self);
+                    report(reporter, "the trees were changed by another session since begin, the transaction is rolled back".to_string(), input, out)?;
+                } else {
+                    self.commit(session)?;
+                }
+            },
+            Command::Rollback => {
+                if committed {
+                    report(reporter, "no transaction to roll back".to_string(), input, out)?;
+                } else {
+                    session.transactions.pop();
+                    session.forget_dropped_current(self);
+                }
+            },
+            command => {
+                self.apply(session, reporter, &name, command, input, out)?;
+            }
+        }
+        if changes && !session.in_transaction() {
+            self.version += 1;
+        }
+        if self.options.validate {
+            if let Some(Err(error)) = self.view(session).trees.get(&name).map(Tree::validate) {
+                return Err(line_error(session.line, error.to_string(), input).into_io(io::ErrorKind::InvalidData));
+            }
+        }
+        if let Some(ref mut wal) = self.wal {
+            wal.maybe_compact(&self.state.trees[DEFAULT_TREE])?;
+        }
+        Ok(true)
+    }
+
+    /// Apply a command which only reads or changes a single tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which sent the command
+    /// * `reporter` - Where errors go
+    /// * `tree_name` - Name of the tree, which exists
+    /// * `command` - The command
+    /// * `input` - The line with the command
+    /// * `out` - Output to which the result of the command is written
+    fn apply(&mut self, session: &mut Session, reporter: Reporter<'_>, tree_name: &str, command: Command, input: &str,
+             out: &mut dyn Write) -> io::Result<()> {
+        let trace = session.trace;
+        let record_history = session.history;
+        let state = top(&mut self.state, session);
+        let record_history = record_history && state.history.is_enabled();
+        let tree = state.trees.get_mut(tree_name).expect("tree of the command exists");
+        match command {
+            Command::Insert{age, name} => {
+                let len = tree.len();
+                let change = record_history.then(|| Change::Inserted{age, name: name.clone()});
+                if trace {
+                    let mut tracer = Tracer::insert((age, &name));
+                    tree.insert_with(age, name, &mut tracer);
+                    write!(out, "{}", tracer.finish(tree))?;
+                } else {
+                    tree.insert(age, name);
+                }
+                if let Some(change) = change.filter(|_| tree.len() != len) {
+                    state.history.record(Record { tree: tree_name.to_string(), change });
+                }
+            },
+            Command::Erase{age, name} => {
+                let len = tree.len();
+                let change = record_history.then(|| Change::Erased{age, name: name.clone()});
+                if trace {
+                    let mut tracer = Tracer::erase((age, &name));
+                    tree.erase_with(age, name, &mut tracer);
+                    write!(out, "{}", tracer.finish(tree))?;
+                } else {
+                    tree.erase(age, name);
+                }
+                if let Some(change) = change.filter(|_| tree.len() != len) {
+                    state.history.record(Record { tree: tree_name.to_string(), change });
+                }
+            },
+            Command::Contains{age, name} => {
+                match tree.find(age,name) {
+                    true => {writeln!(out, "y")?}
+                    false => {
+                        writeln!(out, "n")?;
+                        if self.options.check {
+                            report(reporter, "check failed, the entry is not in the tree".to_string(), input, out)?;
+                        }
+                    }
+                }
+            }
+            Command::Print => {
+                write_tree(out, tree, self.options.format)?;
+            },
+            Command::Dot => {
+                write!(out, "{}", tree.to_dot())?;
+            },
+            Command::Ascii{details} => {
+                let options = AsciiOptions { show_details: details, ..AsciiOptions::default() };
+                write!(out, "{}", tree.render_ascii_with(&options))?;
+            },
+            Command::Reset => {
+                let mut before = mem::take(tree);
+                if record_history {
+                    state.history.record(Record { tree: tree_name.to_string(), change: Change::Replaced{tree: before, command: "x".to_string()} });
+                } else {
+                    before.delete();
+                }
+            },
+            Command::Save{path} => {
+                if let Err(error) = save_snapshot(tree, &path) {
+                    report(reporter, format!("unable to save {}: {}", path, error), input, out)?;
+                }
+            },
+            #[cfg(feature = "stats")]
+            Command::Stats => {
+                write!(out, "{}", tree.stats())?;
+            },
+            #[cfg(not(feature = "stats"))]
+            Command::Stats => {
+                report(reporter, "statistics are not enabled, build with the stats feature.".to_string(), input, out)?;
+            },
+            Command::Size => {
+                writeln!(out, "{}", tree.len())?;
+            },
+            Command::Height => {
+                writeln!(out, "{}", tree.height())?;
+            },
+            Command::Min => {
+                write_entry(out, tree.min())?;
+            },
+            Command::Max => {
+                write_entry(out, tree.max())?;
+            },
+            Command::Rank{age, name} => {
+                writeln!(out, "{}", tree.rank(age, &name))?;
+            },
+            Command::Select{k} => {
+                write_entry(out, tree.select(k))?;
+            },
+            Command::Successor{age, name} => {
+                write_entry(out, tree.successor(age, &name))?;
+            },
+            Command::Predecessor{age, name} => {
+                write_entry(out, tree.predecessor(age, &name))?;
+            },
+            command => unreachable!("{:?} is applied by execute", command)
+        }
+        Ok(())
+    }
+
+    /// Replace the content of a tree, logging the new content if it is the committed default tree.
+    /// Returns an error if the log cannot be written, the tree is unchanged then.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which replaces the content
+    /// * `name` - Name of the tree, which exists
+    /// * `tree` - The new content
+    /// * `command` - The command which replaced the content, for the history
+    fn replace(&mut self, session: &mut Session, name: &str, tree: Tree, command: String) -> io::Result<()> {
+        let record = Record { tree: name.to_string(), change: Change::Replaced{tree, command} };
+        let record = self.make(session, record)?;
+        if session.history {
+            top(&mut self.state, session).history.record(record);
+        }
+        Ok(())
+    }
+
+    /// Make the changes of the innermost transaction of a session part of the state around it.
+    /// Returns an error if the outermost transaction is committed and the log cannot be written,
+    /// the transaction is rolled back then.
+    ///
+    /// # Argument
+    ///
+    /// * `session` - The session, which has an open transaction
+    fn commit(&mut self, session: &mut Session) -> io::Result<()> {
+        let mut state = session.transactions.pop().expect("an open transaction");
+        // The trees were not changed since the outermost `begin`, so its mutations lead to the same tree
+        let log = mem::take(&mut state.log);
+        self.log(session, log)?;
+        let outer = top(&mut self.state, session);
+        let dropped: Vec<String> = outer.trees.keys().filter(|name| !state.trees.contains_key(*name)).cloned().collect();
+        for name in dropped {
+            outer.history.forget(&name);
+        }
+        outer.trees = state.trees;
+        outer.history.append(state.history);
+        Ok(())
+    }
+
+    /// Log mutations of the default tree: to the write-ahead log outside of a transaction, or to the
+    /// innermost transaction, which passes them on when it is committed.
+    /// Returns an error if the write-ahead log cannot be written.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which makes the mutations
+    /// * `mutations` - Mutations which are about to be applied, in order
+    fn log(&mut self, session: &mut Session, mutations: Vec<Mutation>) -> io::Result<()> {
+        let wal = match self.wal {
+            Some(ref mut wal) if !mutations.is_empty() => wal,
+            _ => return Ok(())
+        };
+        match session.transactions.last_mut() {
+            Some(transaction) => transaction.log.extend(mutations),
+            None => wal.append(&mutations)?
+        }
+        Ok(())
+    }
+
+    /// Make a change to its tree, logging it first if it is a change of the default tree.
+    /// Returns the change which was made, or an error if the log cannot be written, the tree is unchanged then.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session which makes the change
+    /// * `record` - The change and the name of its tree, which exists
+    fn make(&mut self, session: &mut Session, mut record: Record) -> io::Result<Record> {
+        if record.tree == DEFAULT_TREE {
+            // The log must not depend on other trees, files or the history, so it gets the full content
+            self.log(session, record.change.mutations())?;
+        }
+        let tree = top(&mut self.state, session).trees.get_mut(&record.tree).expect("tree of the change exists");
+        record.change.apply(tree);
+        Ok(record)
+    }
+}
+
+impl Session {
+    /// Returns true if a transaction is open.
+    pub fn in_transaction(&self) -> bool {
+        !self.transactions.is_empty()
+    }
+
+    /// Write errors to the output as `error: <line error>` lines, instead of reporting them on stderr.
+    pub fn report_errors_to_output(&mut self) {
+        self.errors_to_output = true;
+    }
+
+    /// Refuse `save` and `load` in this session, like `Options::files` does for every session.
+    pub fn disable_files(&mut self) {
+        self.files = false;
+    }
+
+    /// Keep the changes of this session out of the history and refuse `u`, `r` and `history`.
+    ///
+    /// # Discussion
+    ///
+    /// The history of the committed trees is shared, so a session which shares the database with
+    /// others would undo and redo their changes as well.
+    pub fn disable_history(&mut self) {
+        self.history = false;
+    }
+
+    /// Roll back every open transaction, the trees are left exactly as they were before the outermost `begin`.
+    ///
+    /// # Argument
+    ///
+    /// * `database` - The database of the session
+    pub fn rollback_all(&mut self, database: &Database) {
+        self.transactions.clear();
+        self.forget_dropped_current(database);
+    }
+
+    /// Switch back to the default tree if the tree in use does not exist anymore, after a rollback.
+    fn forget_dropped_current(&mut self, database: &Database) {
+        if !database.view(self).trees.contains_key(&self.current) {
+            self.current = DEFAULT_TREE.to_string();
+        }
+    }
+}
+
+impl Interpreter {
+    /// Create an interpreter with an empty default tree, or the tree recovered from the write-ahead log.
+    /// Returns the interpreter, or an error if the log cannot be opened or read.
+    ///
+    /// # Argument
+    ///
+    /// * `options` - Settings of the interpreter
+    pub fn new(options: Options) -> io::Result<Interpreter> {
+        let database = Database::new(options)?;
+        let session = database.session();
+        Ok(Interpreter { database, session })
+    }
+
+    /// Returns the database, without the open transactions of the session.
+    pub fn into_database(self) -> Database {
+        self.database
+    }
+
+    /// Returns the number of bytes which were cut off the end of the write-ahead log on startup, see `Database::discarded`.
+    pub fn discarded(&self) -> u64 {
+        self.database.discarded()
+    }
+
+    /// Returns true if a transaction is open.
+    pub fn in_transaction(&self) -> bool {
+        self.session.in_transaction()
+    }
+
+    /// Roll back every open transaction, the trees are left exactly as they were before the outermost `begin`.
+    pub fn rollback_all(&mut self) {
+        self.session.rollback_all(&self.database);
+    }
+
+    /// Returns the tree in use, as seen inside the innermost open transaction.
+    pub fn tree(&self) -> &Tree {
+        &self.database.view(&self.session).trees[&self.session.current]
+    }
+
+    /// Returns the tree with the given name, as seen inside the innermost open transaction,
+    /// or None if there is no such tree.
+    ///
+    /// # Argument
+    ///
+    /// * `name` - Name of the tree
+    pub fn tree_named(&self, name: &str) -> Option<&Tree> {
+        self.database.view(&self.session).trees.get(name)
+    }
+
+    /// Read commands line by line and apply them until the input ends or `q` is read.
+    /// Returns false if the input ended with `q`, true if it ended at the end of the input.
+    ///
+    /// # Arguments
+    ///
+    /// * `br` - Input from which the commands are read, its lines are numbered from 1
+    /// * `out` - Output to which the results of the commands are written
+    ///
+    /// # Discussion
+    ///
+    /// Errors are handled as described at `command_loop_with`.
+    pub fn run(&mut self, br: &mut dyn BufRead, out: &mut dyn Write) -> io::Result<bool> {
+        self.session.line = 0;
+        loop {
+            let mut input = String::new();
+            match br.read_line(&mut input) {
+                Ok(0) => {
+                    // End of file
+                    return Ok(true);
+                }
+                Ok(_) => {
+                    if !self.execute(&input, out)? {
+                        return Ok(false);
+                    }
+                }
+                Err(error) => {
+                    self.session.line += 1;
+                    let failure = Cell::new(None);
+                    let reporter = Reporter { strict: self.database.options.strict, to_output: false, line: self.session.line, failure: &failure };
+                    if let Err(error) = report(reporter, error.to_string(), &input, out) {
+                        self.rollback_all();
+                        return Err(error);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply a single command.
+    /// Returns false if the command was `q`, true otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The line with the command, it gets the number after the line executed before
+    /// * `out` - Output to which the result of the command is written
+    ///
+    /// # Discussion
+    ///
+    /// An error ends the run, so every open transaction is rolled back when one is returned.
+    pub fn execute(&mut self, input: &str, out: &mut dyn Write) -> io::Result<bool> {
+        self.database.execute(&mut self.session, input, out).map(|outcome| outcome != Outcome::Exit)
+    }
+
+    /// Write a snapshot of the tree in use to the file at the given path, replacing the file if it exists, like `save`.
+    ///
+    /// # Argument
+    ///
+    /// * `path` - Path of the snapshot file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        save_snapshot(self.tree(), path)
+    }
+
+    /// Replace the tree in use by the one in the snapshot file at the given path, like `load`.
+    /// Returns an error if the snapshot cannot be read, or the load cannot be logged.
+    ///
+    /// # Argument
+    ///
+    /// * `path` - Path of the snapshot file
+    pub fn load(&mut self, path: &str) -> Result<(), LoadError> {
+        let loaded = load_snapshot(path).map_err(LoadError::Snapshot)?;
+        let name = self.session.current.clone();
+        self.database.replace(&mut self.session, &name, loaded, format!("load {}", lexer::quote(path))).map_err(LoadError::Log)
+    }
+}
+
+impl Command {
+    /// Returns true if the command may change the trees, or which trees exist.
+    fn changes_trees(&self) -> bool {
+        matches!(self, Command::Insert{..} | Command::Erase{..} | Command::Reset | Command::Load{..}
+                 | Command::New{..} | Command::Drop{..} | Command::Union{..} | Command::Intersection{..}
+                 | Command::Difference{..} | Command::Undo | Command::Redo | Command::Commit)
+    }
+}
+
+/// Returns the state which a session reads and changes, the one of its innermost transaction if there is one.
+///
+/// # Arguments
+///
+/// * `committed` - The committed state of the database
+/// * `session` - The session
+fn top<'a>(committed: &'a mut State, session: &'a mut Session) -> &'a mut State {
+    session.transactions.last_mut().unwrap_or(committed)
+}
+
+/// Returns an error in the given line.
+///
+/// # Arguments
+///
+/// * `line` - Number of the line
+/// * `message` - Description of the error
+/// * `input` - The line
+fn line_error(line: usize, message: String, input: &str) -> LineError {
+    LineError { line, text: input.trim_end_matches(&['\r', '\n'][..]).to_string(), message }
+}
+
+/// Report an error in a line on stderr or the output and keep it as the failure of the command,
+/// or return it in strict mode.
+///
+/// # Arguments
+///
+/// * `reporter` - Where the error goes
+/// * `message` - Description of the error
+/// * `input` - The line
+/// * `out` - Output of the command
+fn report(reporter: Reporter<'_>, message: String, input: &str, out: &mut dyn Write) -> io::Result<()> {
+    let error = line_error(reporter.line, message, input);
+    if reporter.strict {
+        return Err(error.into_io(io::ErrorKind::InvalidInput));
+    }
+    let first = reporter.failure.take().unwrap_or_else(|| error.clone());
+    reporter.failure.set(Some(first));
+    if reporter.to_output {
+        writeln!(out, "error: {}", error)
+    } else {
+        eprintln!("Error: {}", error);
+        Ok(())
+    }
+}
+
+/// Error of `Interpreter::load`
+#[derive(Debug)]
+pub enum LoadError {
+    /// The snapshot cannot be read, the tree is unchanged
+    Snapshot(SnapshotError),
+    /// The load cannot be written to the write-ahead log, the tree is unchanged
+    Log(io::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Snapshot(error) => write!(f, "{}", error),
+            LoadError::Log(error) => write!(f, "unable to log the load: {}", error)
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// Write the whole tree in the given format.
+///
+/// # Arguments
+///
+/// * `out` - Output to which the tree should be written
+/// * `tree` - The tree
+/// * `format` - How the tree should be written
+fn write_tree(out: &mut dyn Write, tree: &Tree, format: Format) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            tree.write_json(out)?;
+            writeln!(out)
+        }
+        Format::Pretty => {
+            tree.write_json_pretty(out)?;
+            writeln!(out)
+        }
+        Format::Dot => write!(out, "{}", tree.to_dot()),
+        Format::Ascii => write!(out, "{}", tree.render_ascii()),
+        Format::SortedList => {
+            for (age, name) in tree.iter() {
+                writeln!(out, "{} {}", age, lexer::quote(name))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write the result of a query for a single entry: `<age> <name>`, or `none` if there is no such entry.
+/// The name is quoted if it is not a valid bare word, so the line can be used as arguments of a command.
+///
+/// # Arguments
+///
+/// * `out` - Output to which the entry should be written
+/// * `entry` - The entry which was found
+fn write_entry(out: &mut dyn Write, entry: Option<(i32, &str)>) -> io::Result<()> {
+    match entry {
+        Some((age, name)) => writeln!(out, "{} {}", age, lexer::quote(name)),
+        None => writeln!(out, "none")
+    }
+}
+
+/// Returns the mutation of the write-ahead log for the given command, or None if it is not a mutation.
+///
+/// # Argument
+///
+/// * `command` - A command which is about to be applied
+fn command_mutation(command: &Command) -> Option<Mutation> {
+    match command {
+        Command::Insert{age, name} => Some(Mutation::Insert{age: *age, name: name.clone()}),
+        Command::Erase{age, name} => Some(Mutation::Erase{age: *age, name: name.clone()}),
+        Command::Reset => Some(Mutation::Reset),
+        _ => None
+    }
+}
+
+/// Write a snapshot of the tree to the file at the given path, replacing the file if it exists.
+///
+/// # Arguments
+///
+/// * `tree` - The tree which should be saved
+/// * `path` - Path of the snapshot file
+fn save_snapshot(tree: &Tree, path: &str) -> io::Result<()> {
+    let file = File::create(path)?;
+    tree.save_to(BufWriter::new(&file))?;
+    file.sync_all()
+}
+
+/// Read a tree from the snapshot file at the given path.
+///
+/// # Argument
+///
+/// * `path` - Path of the snapshot file
+fn load_snapshot(path: &str) -> Result<Tree, SnapshotError> {
+    let file = File::open(path)?;
+    Tree::load_from(BufReader::new(file))
+}
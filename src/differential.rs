@@ -0,0 +1,198 @@
+//! Differential testing of `Tree` against `BTreeSet`, shared by the fuzz targets.
+//!
+//! Enabled with the `arbitrary` cargo feature. Fuzz input is decoded into a sequence of `Op`s,
+//! which are applied to both a `Tree` and a `BTreeSet<(i32, String)>`. After every operation
+//! the tree must be a valid AVL tree, `find` must agree with the set, and the printed tree
+//! must parse back into exactly the data of the set.
+
+use std::collections::BTreeSet;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::Tree;
+
+/// The data of an operation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Key {
+    pub age: i32,
+    pub name: String,
+}
+
+impl<'a> Arbitrary<'a> for Key {
+    /// Mostly keys from a small range, so operations often hit data which is already present
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Key> {
+        if u.ratio(3, 4)? {
+            let age = u.int_in_range(-16..=16)?;
+            let len = u.int_in_range(0..=2)?;
+            let mut name = String::new();
+            for _ in 0..len {
+                name.push(*u.choose(&['a', 'b', 'c'])?);
+            }
+            Ok(Key { age, name })
+        } else {
+            Ok(Key { age: u.arbitrary()?, name: u.arbitrary()? })
+        }
+    }
+}
+
+/// An operation applied to both the tree and the reference set
+#[derive(Arbitrary, Clone, Debug, PartialEq)]
+pub enum Op {
+    Insert(Key),
+    Erase(Key),
+    Find(Key),
+    Print,
+    Reset,
+}
+
+/// Decode the fuzz input into operations and run them, see `run`.
+///
+/// # Argument
+///
+/// * `data` - Raw fuzz input
+pub fn run_bytes(data: &[u8]) -> Result<(), String> {
+    let ops = Vec::<Op>::arbitrary_take_rest(Unstructured::new(data)).map_err(|error| error.to_string())?;
+    run(&ops)
+}
+
+/// Apply the operations to a `Tree` and a `BTreeSet` and compare them after every step.
+/// Returns a description of the first difference.
+///
+/// # Argument
+///
+/// * `ops` - Operations which should be applied in order
+pub fn run(ops: &[Op]) -> Result<(), String> {
+    let mut tree = Tree::new();
+    let mut model = BTreeSet::new();
+    for (step, op) in ops.iter().enumerate() {
+        match op {
+            Op::Insert(Key { age, name }) => {
+                tree.insert(*age, name.clone());
+                model.insert((*age, name.clone()));
+            }
+            Op::Erase(Key { age, name }) => {
+                tree.erase(*age, name.clone());
+                model.remove(&(*age, name.clone()));
+            }
+            Op::Find(Key { age, name }) => {
+                let found = tree.find(*age, name.clone());
+                if found != model.contains(&(*age, name.clone())) {
+                    return Err(format!("step {} ({:?}): find returned {}", step, op, found));
+                }
+            }
+            Op::Print => {
+                let printed = tree.to_string();
+                let parsed = parse_printed(&printed)
+                    .map_err(|error| format!("step {}: unable to parse printed tree {}: {}", step, printed, error))?;
+                if !parsed.iter().eq(model.iter()) {
+                    return Err(format!("step {}: printed tree {} does not hold {:?}", step, printed, model));
+                }
+            }
+            Op::Reset => {
+                tree.delete();
+                tree = Tree::new();
+                model.clear();
+            }
+        }
+        tree.validate().map_err(|error| format!("step {} ({:?}): {}", step, op, error))?;
+        if !tree.iter().eq(model.iter().map(|(age, name)| (*age, name.as_str()))) {
+            return Err(format!("step {} ({:?}): tree holds {:?}, expected {:?}",
+                               step, op, tree.iter().collect::<Vec<_>>(), model));
+        }
+    }
+    Ok(())
+}
+
+/// Parse the output of `Tree::print` independently of the tree.
+/// Returns the data in the order of an in-order walk.
+///
+/// # Argument
+///
+/// * `printed` - The printed tree, without the trailing newline
+fn parse_printed(printed: &str) -> Result<Vec<(i32, String)>, String> {
+    let mut parser = Parser { input: printed.as_bytes(), pos: 0 };
+    let mut entries = Vec::new();
+    parser.subtree(&mut entries)?;
+    if parser.pos != parser.input.len() {
+        return Err(format!("unexpected data at byte {}", parser.pos));
+    }
+    Ok(entries)
+}
+
+/// Recursive descent parser for `null | [{"<age>":"<name>"},<left>,<right>]`
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn subtree(&mut self, entries: &mut Vec<(i32, String)>) -> Result<(), String> {
+        if self.input[self.pos..].starts_with(b"null") {
+            self.pos += 4;
+            return Ok(());
+        }
+        self.expect(b'[')?;
+        self.expect(b'{')?;
+        let age = self.string()?.parse::<i32>().map_err(|error| error.to_string())?;
+        self.expect(b':')?;
+        let name = self.string()?;
+        self.expect(b'}')?;
+        self.expect(b',')?;
+        self.subtree(entries)?;
+        entries.push((age, name));
+        self.expect(b',')?;
+        self.subtree(entries)?;
+        self.expect(b']')
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.input.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at byte {}", byte as char, self.pos))
+        }
+    }
+
+    /// Parse a JSON string and undo its escapes.
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            match self.input.get(self.pos) {
+                None => return Err("unterminated string".to_string()),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let escape = self.input.get(self.pos + 1).ok_or("unterminated escape")?;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'u' => {
+                            let hex = self.input.get(self.pos + 2..self.pos + 6).ok_or("short \\u escape")?;
+                            let hex = std::str::from_utf8(hex).map_err(|error| error.to_string())?;
+                            let code = u32::from_str_radix(hex, 16).map_err(|error| error.to_string())?;
+                            self.pos += 4;
+                            std::char::from_u32(code).ok_or("\\u escape of a surrogate")?
+                        }
+                        _ => return Err(format!("invalid escape at byte {}", self.pos))
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    self.pos += 2;
+                    continue;
+                }
+                Some(&byte) if byte < 0x20 => return Err(format!("unescaped control character at byte {}", self.pos)),
+                Some(&byte) => bytes.push(byte),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        String::from_utf8(bytes).map_err(|error| error.to_string())
+    }
+}
@@ -0,0 +1,185 @@
+//! Undo and redo of the mutations made by the command interpreter, see `u`, `r` and `history`.
+//!
+//! Every mutation which changed a tree is recorded with enough information to invert it:
+//! the entry of an insert or erase, or the whole previous tree of a mutation which replaced
+//! the content of a tree, like `x`, `load` or `union`. Undoing a change inverts it and moves
+//! it to the redo list, redoing it inverts it again. A new mutation clears the redo list.
+
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::lexer;
+use crate::tree::Tree;
+use crate::wal::{content_mutations, Mutation};
+
+/// Number of mutations which can be undone by default
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// A change of a single tree
+#[derive(Debug)]
+pub enum Change {
+    /// An entry which was not in the tree was inserted
+    Inserted{age: i32, name: String},
+    /// An entry which was in the tree was erased
+    Erased{age: i32, name: String},
+    /// The content of the tree was replaced, `tree` holds the other version of the tree
+    Replaced{tree: Tree, command: String},
+}
+
+impl Change {
+    /// Returns the change which undoes this one. A replacement is its own inverse, as applying it
+    /// swaps the tree with the stored version.
+    fn invert(self) -> Change {
+        match self {
+            Change::Inserted{age, name} => Change::Erased{age, name},
+            Change::Erased{age, name} => Change::Inserted{age, name},
+            replaced => replaced
+        }
+    }
+
+    /// Returns the mutations which the write-ahead log needs to make the same change.
+    pub fn mutations(&self) -> Vec<Mutation> {
+        match self {
+            Change::Inserted{age, name} => vec![Mutation::Insert{age: *age, name: name.clone()}],
+            Change::Erased{age, name} => vec![Mutation::Erase{age: *age, name: name.clone()}],
+            Change::Replaced{tree, ..} => content_mutations(tree)
+        }
+    }
+
+    /// Make the change.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree in the state before the change
+    pub fn apply(&mut self, tree: &mut Tree) {
+        match self {
+            Change::Inserted{age, name} => tree.insert(*age, name.clone()),
+            Change::Erased{age, name} => tree.erase(*age, name.clone()),
+            Change::Replaced{tree: other, ..} => mem::swap(tree, other)
+        }
+    }
+
+    /// Returns the command which made the change.
+    fn command(&self) -> String {
+        match self {
+            Change::Inserted{age, name} => format!("i {} {}", age, lexer::quote(name)),
+            Change::Erased{age, name} => format!("e {} {}", age, lexer::quote(name)),
+            Change::Replaced{command, ..} => command.clone()
+        }
+    }
+}
+
+/// A change with the name of the tree it was made to
+#[derive(Debug)]
+pub struct Record {
+    /// Name of the tree
+    pub tree: String,
+    /// The change
+    pub change: Change,
+}
+
+impl Record {
+    /// Returns the command which made the change, targeted at its tree like `@<tree> <command>`.
+    pub fn command(&self) -> String {
+        format!("@{} {}", lexer::quote(&self.tree), self.change.command())
+    }
+}
+
+/// The changes which can be undone and redone
+#[derive(Debug)]
+pub struct History {
+    /// Changes which can be undone, the most recent one last
+    undo: VecDeque<Record>,
+    /// Changes which were undone and can be redone, the most recently undone one last
+    redo: Vec<Record>,
+    /// Most changes kept in `undo`
+    depth: usize,
+}
+
+impl History {
+    /// Create an empty history.
+    ///
+    /// # Argument
+    ///
+    /// * `depth` - Number of changes which can be undone, 0 records nothing
+    pub fn new(depth: usize) -> History {
+        History { undo: VecDeque::new(), redo: Vec::new(), depth }
+    }
+
+    /// Returns true if changes are recorded, so the caller can avoid preparing a record.
+    pub fn is_enabled(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Record a change which was just made, the changes which were undone cannot be redone anymore.
+    ///
+    /// # Argument
+    ///
+    /// * `record` - The change
+    pub fn record(&mut self, record: Record) {
+        self.redo.clear();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(record);
+    }
+
+    /// Returns the most recent change, inverted so applying it undoes the change, or None if
+    /// there is nothing to undo. Pass it to `undone` once it is applied.
+    pub fn undo(&mut self) -> Option<Record> {
+        self.undo.pop_back().map(|record| Record { tree: record.tree, change: record.change.invert() })
+    }
+
+    /// Keep a change returned by `undo` for `redo`.
+    ///
+    /// # Argument
+    ///
+    /// * `record` - The applied inverse change
+    pub fn undone(&mut self, record: Record) {
+        self.redo.push(record);
+    }
+
+    /// Returns the most recently undone change, inverted so applying it makes the change again,
+    /// or None if there is nothing to redo. Pass it to `redone` once it is applied.
+    pub fn redo(&mut self) -> Option<Record> {
+        self.redo.pop().map(|record| Record { tree: record.tree, change: record.change.invert() })
+    }
+
+    /// Keep a change returned by `redo` for `undo`.
+    ///
+    /// # Argument
+    ///
+    /// * `record` - The change which was made again
+    pub fn redone(&mut self, record: Record) {
+        self.undo.push_back(record);
+    }
+
+    /// Record the changes of another history after those of this one, when a transaction is committed.
+    ///
+    /// # Argument
+    ///
+    /// * `other` - The history of the transaction
+    pub fn append(&mut self, other: History) {
+        for record in other.undo {
+            self.record(record);
+        }
+    }
+
+    /// Forget every change of a tree, when it is dropped.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - Name of the tree
+    pub fn forget(&mut self, tree: &str) {
+        self.undo.retain(|record| record.tree != tree);
+        self.redo.retain(|record| record.tree != tree);
+    }
+
+    /// Returns the changes which can be undone, the oldest first.
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.undo.iter()
+    }
+}
@@ -0,0 +1,197 @@
+//! Lexer of the command language.
+//!
+//! A line is split into words separated by whitespace. A word which starts with `"` is a quoted
+//! string: it ends at the next unescaped `"`, may contain whitespace and understands the escapes
+//! `\"`, `\\`, `\n`, `\t` and `\u{<hex>}`. Any other word is a bare word and is taken as it is,
+//! quotes and backslashes included, so scripts written before quoting existed keep working.
+//! A word which starts with `@"` is `@` followed by a quoted string and is read as the bare word
+//! `@<string>`, so `@<tree>` can name any tree.
+//!
+//! Columns count characters and start at 1.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A word of a command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    /// The word, with the quotes removed and the escapes replaced
+    pub text: String,
+    /// Column of the first character of the word
+    pub column: usize,
+    /// Whether the word was a quoted string
+    pub quoted: bool,
+}
+
+/// A line which cannot be split into words
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    /// Column of the character where the problem was found
+    pub column: usize,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl Error for LexError {}
+
+/// Split a command line into words.
+/// Returns the words in order, or the first problem found.
+///
+/// # Argument
+///
+/// * `line` - The command line, a trailing newline is ignored
+pub fn tokenize(line: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer { chars: line.chars().peekable(), column: 0 };
+    let mut tokens = Vec::new();
+    while let Some(c) = lexer.peek() {
+        if c.is_whitespace() {
+            lexer.next();
+        } else if c == '"' {
+            tokens.push(lexer.quoted()?);
+        } else if c == '@' && lexer.chars.clone().nth(1) == Some('"') {
+            tokens.push(lexer.target()?);
+        } else {
+            tokens.push(lexer.bare());
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the name as a word which `tokenize` reads back as the same name:
+/// the name itself if it is a valid bare word, or a quoted string otherwise.
+///
+/// # Argument
+///
+/// * `name` - The name which should be written
+pub fn quote(name: &str) -> Cow<'_, str> {
+    let bare = !name.is_empty() && !name.starts_with('"') && !name.starts_with("@\"") && !name.chars().any(|c| c.is_whitespace() || c.is_control());
+    if bare {
+        return Cow::Borrowed(name);
+    }
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Characters of a line with the column of the last character taken
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c.is_some() {
+            self.column += 1;
+        }
+        c
+    }
+
+    /// Returns an error at the given column.
+    fn error(column: usize, message: &str) -> LexError {
+        LexError { column, message: message.to_string() }
+    }
+
+    /// Read a bare word, which ends at whitespace or the end of the line.
+    fn bare(&mut self) -> Token {
+        let column = self.column + 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+        Token { text, column, quoted: false }
+    }
+
+    /// Read `@` followed by a quoted string as the bare word `@<string>`.
+    fn target(&mut self) -> Result<Token, LexError> {
+        self.next();
+        let column = self.column;
+        let token = self.quoted()?;
+        Ok(Token { text: format!("@{}", token.text), column, quoted: false })
+    }
+
+    /// Read a quoted string, starting at its opening quote.
+    fn quoted(&mut self) -> Result<Token, LexError> {
+        self.next();
+        let column = self.column;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                None => return Err(Lexer::error(column, "unterminated string")),
+                Some('"') => break,
+                Some('\\') => text.push(self.escape(column)?),
+                Some(c) => text.push(c)
+            }
+        }
+        match self.peek() {
+            Some(c) if !c.is_whitespace() => Err(Lexer::error(self.column + 1, "expected whitespace after closing quote")),
+            _ => Ok(Token { text, column, quoted: true })
+        }
+    }
+
+    /// Read the rest of an escape, after its backslash.
+    /// Returns the character the escape stands for.
+    ///
+    /// # Argument
+    ///
+    /// * `start` - Column of the opening quote of the string
+    fn escape(&mut self, start: usize) -> Result<char, LexError> {
+        let column = self.column;
+        match self.next() {
+            Some('"') => Ok('"'),
+            Some('\\') => Ok('\\'),
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('u') => {
+                if self.next() != Some('{') {
+                    return Err(Lexer::error(column, "expected { after \\u"));
+                }
+                let mut hex = String::new();
+                loop {
+                    match self.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+                        _ => return Err(Lexer::error(column, "expected 1 to 6 hex digits and } in \\u{..}"))
+                    }
+                }
+                if hex.is_empty() {
+                    return Err(Lexer::error(column, "expected 1 to 6 hex digits and } in \\u{..}"));
+                }
+                u32::from_str_radix(&hex, 16).ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| Lexer::error(column, &format!("\\u{{{}}} is not a unicode scalar value", hex)))
+            }
+            Some(c) => Err(Lexer::error(column, &format!("unknown escape \\{}", c))),
+            None => Err(Lexer::error(start, "unterminated string"))
+        }
+    }
+}
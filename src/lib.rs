@@ -0,0 +1,34 @@
+pub mod command;
+#[cfg(feature = "arbitrary")]
+pub mod differential;
+pub mod history;
+pub mod lexer;
+pub mod server;
+pub mod trace;
+pub mod tree;
+pub mod wal;
+pub mod workload;
+mod test_command;
+#[cfg(feature = "arbitrary")]
+mod test_differential;
+mod test_history;
+mod test_http;
+mod test_lexer;
+mod test_model;
+mod test_observer;
+mod test_query;
+mod test_resp;
+#[cfg(feature = "serde")]
+mod test_serde;
+mod test_server;
+mod test_set_ops;
+mod test_snapshot;
+#[cfg(feature = "stats")]
+mod test_stats;
+mod test_transaction;
+mod test_tree;
+mod test_wal;
+mod test_workload;
+
+pub use command::{command_loop, command_loop_with, Database, Format, Interpreter, LineError, LoadError, Options, Outcome, Session};
+pub use tree::{AsciiOptions, Tree, TreeObserver};
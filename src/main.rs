@@ -1,104 +1,217 @@
-use std::io::{self, BufRead};
-mod tree;
-mod test_tree;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
 
-use tree::Tree;
+use rustsint::history::DEFAULT_HISTORY_DEPTH;
+use rustsint::wal::WalOptions;
+use rustsint::server::{Protocol, Server};
+use rustsint::{Interpreter, Options};
 
-#[derive(Debug)]
-enum Command {
-    Insert{age: i32, name: String},
-    Erase{age: i32, name: String},
-    Contains{age: i32, name: String},
-    Print,
-    Reset,
-    Exit,
-    Error(String)
+const USAGE: &str = "usage: rustsint [--input <file>]... [--output <file>] [--format json|pretty|dot|ascii|sorted-list]
+                [--quiet] [--listen <addr> [--protocol lines|http|resp]] [--load <snapshot>] [--save <snapshot>]
+                [--trace] [--strict] [--check] [--history <depth>]
+                [--wal <log> [--snapshot <file>] [--compact-after <records>]] [--help] [--version]";
+
+const HELP: &str = "Read commands and apply them to an AVL tree of (age, name) entries.
+
+options:
+  --input <file>            read commands from the file instead of stdin, may be repeated,
+                            the files are run in order on the same tree, - is stdin
+  --output <file>           write the results to the file instead of stdout
+  --format <format>         how p writes the tree: json (default), pretty, dot, ascii or sorted-list
+  --quiet                   discard the results, errors are still reported on stderr
+  --listen <addr>           serve the commands over TCP instead, e.g. 127.0.0.1:7878,
+                            until a client sends shutdown
+  --protocol <protocol>     how the clients talk to the server: lines (default), one command
+                            and one response per line, http, a REST API with JSON responses,
+                            or resp, the sorted set commands of Redis
+  --load <snapshot>         load the default tree from a snapshot before the first command
+  --save <snapshot>         save the default tree to a snapshot after the last command
+  --trace                   start in trace mode, see t on
+  --strict                  stop at the first error with exit code 1, an input which ends
+                            inside a transaction is an error as well
+  --check                   like --strict, and a c of a missing entry is an error
+  --history <depth>         number of mutations u can undo, 100 by default, 0 disables undo
+  --wal <log>               log every mutation and recover the tree from the log on startup
+  --snapshot <file>         snapshot of the log, written when the log is compacted
+  --compact-after <records> compact the log once it holds this many records
+  --help                    print this help
+  --version                 print the version";
+
+/// Settings of the binary
+struct Args {
+    /// Settings of the interpreter
+    options: Options,
+    /// Files from which the commands are read in order, stdin if empty
+    inputs: Vec<String>,
+    /// File to which the results are written, stdout if None
+    output: Option<String>,
+    /// Discard the results
+    quiet: bool,
+    /// Address on which the commands are served over TCP instead of reading the inputs
+    listen: Option<String>,
+    /// How the clients talk to the server
+    protocol: Protocol,
+    /// Snapshot of the default tree loaded before the first command
+    load: Option<String>,
+    /// Snapshot of the default tree saved after the last command
+    save: Option<String>,
 }
 
-fn parse_command(input: String) -> Command {
-    let command_items: Vec<&str> = input.split_whitespace().collect();
-    if command_items.len() == 0 {
-        Command::Error("invalid command (empty line)".to_string())
-    } else {
-        match (command_items[0], command_items.len()) {
-            ("p", 1) => Command::Print,
-            ("q", 1) => Command::Exit,
-            ("x", 1) => Command::Reset,
-            ("i", 3) => {
-                if let Ok(age) = command_items[1].parse::<i32>() {
-                    Command::Insert{age: age, name: command_items[2].to_string()}
-                } else {
-                    Command::Error("unable to parse int (age).".to_string())
-                }
-            },
-            ("e", 3) => {
-                if let Ok(age) = command_items[1].parse::<i32>() {
-                    Command::Erase{age: age, name: command_items[2].to_string()}
-                } else {
-                    Command::Error("unable to parse int (age).".to_string())
-                }
-            },
-            ("c", 3) => {
-                if let Ok(age) = command_items[1].parse::<i32>() {
-                    Command::Contains{age: age, name: command_items[2].to_string()}
-                } else {
-                    Command::Error("unable to parse int (age).".to_string())
-                }
-            },
+/// What the binary should do
+enum Action {
+    Run(Args),
+    Help,
+    Version,
+}
 
-            (_, _) => Command::Error("invalid command.".to_string())
+/// Parse the command line arguments into the settings of the binary.
+/// Returns the action, or a message describing the invalid argument.
+fn parse_args(args: &[String]) -> Result<Action, String> {
+    let mut log_path = None;
+    let mut snapshot_path = None;
+    let mut compact_after = 0;
+    let mut trace = false;
+    let mut strict = false;
+    let mut check = false;
+    let mut format = Default::default();
+    let mut history = DEFAULT_HISTORY_DEPTH;
+    let mut inputs = Vec::new();
+    let mut output = None;
+    let mut quiet = false;
+    let mut listen = None;
+    let mut protocol = None;
+    let mut load = None;
+    let mut save = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(Action::Help),
+            "--version" | "-V" => return Ok(Action::Version),
+            "--input" => inputs.push(value()?.clone()),
+            "--output" => output = Some(value()?.clone()),
+            "--format" => format = value()?.parse()?,
+            "--quiet" => quiet = true,
+            "--listen" => listen = Some(value()?.clone()),
+            "--protocol" => protocol = Some(value()?.parse()?),
+            "--load" => load = Some(value()?.clone()),
+            "--save" => save = Some(value()?.clone()),
+            "--trace" => trace = true,
+            "--strict" => strict = true,
+            "--check" => {
+                check = true;
+                strict = true;
+            }
+            "--history" => history = value()?.parse().map_err(|_| "--history expects a number".to_string())?,
+            "--wal" => log_path = Some(PathBuf::from(value()?)),
+            "--snapshot" => snapshot_path = Some(PathBuf::from(value()?)),
+            "--compact-after" => {
+                compact_after = value()?.parse().map_err(|_| "--compact-after expects a number".to_string())?
+            }
+            _ => return Err(format!("unknown argument {}", arg))
         }
     }
+    let wal = match log_path {
+        Some(log_path) => Some(WalOptions { log_path, snapshot_path, compact_after }),
+        None if snapshot_path.is_some() => return Err("--snapshot requires --wal".to_string()),
+        None => None
+    };
+    if listen.is_some() && (!inputs.is_empty() || output.is_some()) {
+        return Err("--listen cannot be combined with --input or --output".to_string());
+    }
+    if listen.is_none() && protocol.is_some() {
+        return Err("--protocol requires --listen".to_string());
+    }
+    let protocol = protocol.unwrap_or_default();
+    let options = Options { wal, trace, strict, check, format, history, ..Options::default() };
+    Ok(Action::Run(Args { options, inputs, output, quiet, listen, protocol, load, save }))
 }
 
-pub fn command_loop(br: &mut dyn BufRead) {
-
-    let mut tree = Tree::new();
+/// Run the inputs in order on a single tree, or serve the tree over TCP, between the optional load and save.
+/// Returns a message describing the first error which stopped the run.
+fn run(args: Args) -> Result<(), String> {
+    let strict = args.options.strict;
+    let log_path = args.options.wal.as_ref().map(|wal| wal.log_path.clone());
+    let mut interpreter = Interpreter::new(args.options).map_err(|error| error.to_string())?;
+    if let Some(path) = log_path.filter(|_| interpreter.discarded() > 0) {
+        eprintln!("Warning: discarded an incomplete record of {} bytes at the end of {}", interpreter.discarded(), path.display());
+    }
+    if let Some(ref path) = args.load {
+        interpreter.load(path).map_err(|error| format!("unable to load {}: {}", path, error))?;
+    }
+    if let Some(ref address) = args.listen {
+        let server = Server::bind(address, args.protocol, interpreter.into_database())
+            .map_err(|error| format!("unable to listen on {}: {}", address, error))?;
+        eprintln!("Listening on {}", server.local_addr());
+        let database = server.run().map_err(|error| error.to_string())?;
+        if let Some(ref path) = args.save {
+            database.save(path).map_err(|error| format!("unable to save {}: {}", path, error))?;
+        }
+        return Ok(());
+    }
 
-    loop {
-        let mut input = String::new();
-        
-        match br.read_line(&mut input) {
-            Ok(0) => {
-                // End of file
-                break;
-            }
-            Ok(_) => {
-                match parse_command(input) {
-                    Command::Insert{age, name} => {
-                        tree.insert(age, name);
-                    },
-                    Command::Erase{age, name} => {
-                        tree.erase(age, name);
-                    },
-                    Command::Contains{age, name} => {
-                        match tree.find(age,name) {
-                            true => {println!("y")}
-                            false => {println!("n")}
-                        }
-                    }
-                    Command::Print => {
-                        tree.print();
-                    },
-                    Command::Reset => {
-                        tree.delete();
-                        tree = Tree::new();
-                    },
-                    Command::Exit => {
-                        break;
-                    },
-                    Command::Error(error) => {
-                        eprintln!("Error: {}", error);
-                    }
-                }
-            }
-            Err(error) => eprintln!("Error: {}", error),
+    let mut out: Box<dyn Write> = match args.output {
+        _ if args.quiet => Box::new(io::sink()),
+        Some(ref path) => {
+            let file = File::create(path).map_err(|error| format!("unable to create {}: {}", path, error))?;
+            Box::new(BufWriter::new(file))
         }
+        None => Box::new(io::stdout())
+    };
+    let inputs = if args.inputs.is_empty() { vec!["-".to_string()] } else { args.inputs };
+    for input in &inputs {
+        let mut reader: Box<dyn BufRead> = if input == "-" {
+            Box::new(BufReader::new(io::stdin()))
+        } else {
+            let file = File::open(input).map_err(|error| format!("unable to open {}: {}", input, error))?;
+            Box::new(BufReader::new(file))
+        };
+        // `q` ends the run, not only the current input
+        let name = if input == "-" { "stdin" } else { input };
+        let more = interpreter.run(&mut reader, &mut out).map_err(|error| format!("{}: {}", name, error))?;
+        if !more {
+            break;
+        }
+    }
+    out.flush().map_err(|error| error.to_string())?;
+    if interpreter.in_transaction() {
+        interpreter.rollback_all();
+        if strict {
+            return Err("the input ended inside a transaction, it is rolled back".to_string());
+        }
+        eprintln!("Error: the input ended inside a transaction, it is rolled back");
+    }
+
+    // Like in server mode the default tree is saved, not the one which the input left in use
+    if let Some(ref path) = args.save {
+        interpreter.into_database().save(path).map_err(|error| format!("unable to save {}: {}", path, error))?;
     }
+    Ok(())
 }
 
 fn main() {
-    let stdin = io::stdin();
-    let mut handle = stdin.lock();
-    command_loop(&mut handle);
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args = match parse_args(&args) {
+        Ok(Action::Run(args)) => args,
+        Ok(Action::Help) => {
+            println!("{}\n\n{}", USAGE, HELP);
+            return;
+        }
+        Ok(Action::Version) => {
+            println!("rustsint {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(error) => {
+            eprintln!("Error: {}\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(args) {
+        eprintln!("Error: {}", error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,372 @@
+//! TCP server which serves the command language to many clients sharing one database.
+//!
+//! Every connection gets its own session, so it has its own tree in use, trace mode, line numbers
+//! and transactions, while the trees are shared. A client sends one command per line and gets
+//! exactly one line back for every command:
+//!
+//! | Output of the command | Response                                                    |
+//! |-----------------------|-------------------------------------------------------------|
+//! | nothing               | `ok`                                                        |
+//! | a single line         | the line                                                    |
+//! | several lines         | the output as one double-quoted string, see `lexer::quote`  |
+//! | an error              | `error: line <n>: ...`, the connection stays open           |
+//!
+//! A line longer than `MAX_LINE` is skipped and answered with an error as well.
+//!
+//! The server can also speak HTTP, see `http`, and the sorted set commands of Redis, see `resp`.
+//!
+//! `q` closes the connection, `shutdown` stops the server if the client runs on the same machine,
+//! see `is_local`. Only such clients may `save` and `load` files as well. `u`, `r` and `history`
+//! are refused, as one client would undo the changes of another. Commands are applied one at a time,
+//! each holding the lock of the database, so the commands of different clients never interleave.
+//! On shutdown the server stops accepting connections, lets every client finish the command it is
+//! running, closes the connections and rolls back their open transactions.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::command::{Database, Outcome, Session};
+use crate::lexer;
+
+mod http;
+mod resp;
+
+/// Command which stops the server
+const SHUTDOWN: &str = "shutdown";
+
+/// Longest command line in bytes, including its line break
+const MAX_LINE: u64 = 64 * 1024;
+
+/// Error answered to a shutdown from a client which may not stop the server
+const SHUTDOWN_REFUSED: &str = "shutdown is only accepted from the local machine";
+
+/// How the clients talk to a server
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// One command per line and one response line per command, see the top of this module
+    #[default]
+    Lines,
+    /// REST requests with JSON responses over HTTP/1.1, see `http`
+    Http,
+    /// Sorted set commands of Redis in the RESP2 encoding, see `resp`
+    Resp,
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    /// Parse `lines`, `http` or `resp`.
+    fn from_str(s: &str) -> Result<Protocol, String> {
+        match s {
+            "lines" => Ok(Protocol::Lines),
+            "http" => Ok(Protocol::Http),
+            "resp" => Ok(Protocol::Resp),
+            _ => Err(format!("unknown protocol {}, expected lines, http or resp", s))
+        }
+    }
+}
+
+/// A TCP server bound to an address, see `run`
+pub struct Server {
+    listener: TcpListener,
+    protocol: Protocol,
+    database: Arc<Mutex<Database>>,
+    handle: ShutdownHandle,
+}
+
+/// Stops a running server from another thread, see `Server::shutdown_handle`
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    /// Set once the server is stopping
+    stopping: Arc<AtomicBool>,
+    /// Address of the server, connected to once to wake up `accept`
+    address: SocketAddr,
+    /// The open connections by number, shut down when the server stops
+    connections: Arc<Mutex<HashMap<usize, TcpStream>>>,
+}
+
+impl Server {
+    /// Bind a server to an address.
+    /// Returns the server, or an error if the address cannot be bound.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address to listen on, port 0 picks a free port, see `local_addr`
+    /// * `protocol` - How the clients talk to the server
+    /// * `database` - The trees which the clients share
+    pub fn bind<A: ToSocketAddrs>(address: A, protocol: Protocol, database: Database) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        let handle = ShutdownHandle {
+            stopping: Arc::new(AtomicBool::new(false)),
+            address: listener.local_addr()?,
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        };
+        Ok(Server { listener, protocol, database: Arc::new(Mutex::new(database)), handle })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.handle.address
+    }
+
+    /// Returns a handle which stops the server.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.handle.clone()
+    }
+
+    /// Accept connections and serve each of them on its own thread, until the server is shut down.
+    /// Returns the database once every connection is closed, or an error if accepting fails.
+    ///
+    /// # Discussion
+    ///
+    /// An error of a single connection, like a client which disappears, only closes that connection.
+    pub fn run(self) -> io::Result<Database> {
+        let mut workers: Vec<JoinHandle<()>> = Vec::new();
+        for (number, stream) in self.listener.incoming().enumerate() {
+            if self.handle.stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("Error: unable to accept a connection: {}", error);
+                    continue;
+                }
+            };
+            let database = Arc::clone(&self.database);
+            let handle = self.handle.clone();
+            let protocol = self.protocol;
+            workers.retain(|worker| !worker.is_finished());
+            workers.push(thread::spawn(move || {
+                let peer = stream.peer_addr().map(|address| address.to_string()).unwrap_or_else(|_| "client".to_string());
+                let result = match handle.register(number, &stream) {
+                    Ok(false) => Ok(()),
+                    Ok(true) => match protocol {
+                        Protocol::Lines => serve(stream, &database, &handle),
+                        Protocol::Http => http::serve(stream, &database, &handle),
+                        Protocol::Resp => resp::serve(stream, &database, &handle),
+                    },
+                    Err(error) => Err(error)
+                };
+                if let Err(error) = result {
+                    eprintln!("Error: {}: {}", peer, error);
+                }
+                lock(&handle.connections).remove(&number);
+            }));
+        }
+        self.handle.close_connections();
+        for worker in workers {
+            // A panic of a connection is already reported on stderr
+            let _ = worker.join();
+        }
+        match Arc::try_unwrap(self.database) {
+            Ok(database) => Ok(database.into_inner().unwrap_or_else(|error| error.into_inner())),
+            Err(_) => unreachable!("every connection is closed")
+        }
+    }
+}
+
+impl ShutdownHandle {
+    /// Stop the server: no new connection is accepted and the open ones are closed
+    /// once their current command is answered.
+    pub fn shutdown(&self) {
+        if self.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Wake up `accept`, which then sees the flag
+        let _ = TcpStream::connect(self.address);
+        self.close_connections();
+    }
+
+    /// Keep a connection, so it can be closed when the server stops.
+    /// Returns false if the server is already stopping, the connection should be closed then.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - Number of the connection
+    /// * `stream` - The connection
+    fn register(&self, number: usize, stream: &TcpStream) -> io::Result<bool> {
+        // Every response is written at once, so there is nothing to gain from delaying it
+        stream.set_nodelay(true)?;
+        lock(&self.connections).insert(number, stream.try_clone()?);
+        // The server may have stopped between accepting and registering the connection
+        Ok(!self.stopping.load(Ordering::SeqCst))
+    }
+
+    /// Stop reading from every open connection, which ends it after its current command.
+    fn close_connections(&self) {
+        for stream in lock(&self.connections).values() {
+            let _ = stream.shutdown(Shutdown::Read);
+        }
+    }
+}
+
+/// Serve the commands of a single connection until the client quits or disconnects, or the server stops.
+/// Returns an error if the connection fails.
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `database` - The shared trees
+/// * `handle` - Handle of the server
+fn serve(stream: TcpStream, database: &Mutex<Database>, handle: &ShutdownHandle) -> io::Result<()> {
+    let local = is_local(stream.peer_addr()?.ip());
+    let mut session = client_session(&lock(database), local);
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    loop {
+        let input = match read_line(&mut reader) {
+            Ok(Some(input)) => input,
+            Ok(None) => return Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                writeln!(writer, "error: {}", error)?;
+                writer.flush()?;
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+        if input.trim() == SHUTDOWN && !local {
+            writeln!(writer, "error: {}", SHUTDOWN_REFUSED)?;
+            writer.flush()?;
+            continue;
+        }
+        if input.trim() == SHUTDOWN {
+            writeln!(writer, "ok")?;
+            writer.flush()?;
+            handle.shutdown();
+            return Ok(());
+        }
+        let mut output = Vec::new();
+        let result = lock(database).execute(&mut session, &input, &mut output);
+        match result {
+            Ok(Outcome::Exit) => return Ok(()),
+            Ok(_) => write_response(&mut writer, &output)?,
+            // Strict mode ends the session at the first error
+            Err(error) => {
+                writeln!(writer, "error: {}", error)?;
+                writer.flush()?;
+                return Ok(());
+            }
+        }
+        writer.flush()?;
+    }
+}
+
+/// Read the next command line.
+/// Returns the line, None if the client closed the connection, or an error of kind `InvalidData`
+/// if the line is longer than `MAX_LINE` or not UTF-8. The whole line is consumed also then,
+/// so the next line can be read.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn read_line(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if (&mut *reader).take(MAX_LINE).read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") && line.len() as u64 == MAX_LINE {
+        skip_line(reader)?;
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("a line is longer than {} bytes", MAX_LINE)));
+    }
+    String::from_utf8(line).map(Some).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "a line is not UTF-8"))
+}
+
+/// Discard the rest of a line, without keeping it in memory.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn skip_line(reader: &mut dyn BufRead) -> io::Result<()> {
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        match buffer.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(());
+            }
+            None => {
+                let len = buffer.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Write the output of a command as a single response line.
+///
+/// # Arguments
+///
+/// * `out` - The connection
+/// * `output` - Output of the command
+fn write_response(out: &mut dyn Write, output: &[u8]) -> io::Result<()> {
+    let output = String::from_utf8_lossy(output);
+    let output = output.strip_suffix('\n').unwrap_or(&output);
+    if output.is_empty() {
+        writeln!(out, "ok")
+    } else if output.contains('\n') {
+        writeln!(out, "{}", lexer::quote(output))
+    } else {
+        writeln!(out, "{}", output)
+    }
+}
+
+/// Apply a command whose output is not needed, like an insert made on behalf of a client of another protocol.
+/// Returns the description of the error if the command failed.
+///
+/// # Arguments
+///
+/// * `database` - The locked database
+/// * `session` - The session of the connection, which reports errors to its output
+/// * `input` - The command
+fn apply(database: &mut Database, session: &mut Session, input: &str) -> Result<(), String> {
+    match database.execute(session, input, &mut io::sink()) {
+        Ok(Outcome::Failed(error)) => Err(error.to_string()),
+        Ok(_) => Ok(()),
+        Err(error) => Err(error.to_string())
+    }
+}
+
+/// Returns true if a client at the given address runs on the same machine as the server.
+/// Only such clients may stop the server and `save` or `load` files, as every other client can reach it as well.
+///
+/// # Argument
+///
+/// * `peer` - Address of the client
+pub(crate) fn is_local(peer: IpAddr) -> bool {
+    peer.to_canonical().is_loopback()
+}
+
+/// Returns a new session for a client, which reports errors to its output and may only use files if it is local.
+/// Its changes are kept out of the history, which the clients share, see `Session::disable_history`.
+///
+/// # Arguments
+///
+/// * `database` - The shared trees
+/// * `local` - Whether the client runs on the same machine, see `is_local`
+pub(crate) fn client_session(database: &Database, local: bool) -> Session {
+    let mut session = database.session();
+    session.report_errors_to_output();
+    session.disable_history();
+    if !local {
+        session.disable_files();
+    }
+    session
+}
+
+/// Returns the guard of a mutex, also if another thread panicked while holding it.
+///
+/// # Argument
+///
+/// * `mutex` - The mutex
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|error| error.into_inner())
+}
@@ -0,0 +1,385 @@
+//! HTTP/1.1 front end of the server, which maps REST requests onto the default tree.
+//!
+//! | Request                        | Response                                                        |
+//! |--------------------------------|-----------------------------------------------------------------|
+//! | `PUT /entries/{age}/{name}`    | 201 and the entry if it was inserted, 200 if it was already there |
+//! | `DELETE /entries/{age}/{name}` | 200 and the entry if it was erased, 404 if it is not in the tree |
+//! | `GET /entries/{age}/{name}`    | 200 and the entry, 404 if it is not in the tree                 |
+//! | `GET /entries?from=&to=`       | 200 and the entries with an age in the range, both ends included and optional |
+//! | `GET /tree`                    | 200 and the tree in the JSON structure printed by `p`            |
+//! | `GET /stats`                   | 200 and the size and height, with the `stats` feature also the counters |
+//! | `GET /health`                  | 200 and `{"status":"ok"}`                                       |
+//! | `POST /shutdown`               | 200, then the server stops like after `shutdown`, 403 from another machine |
+//!
+//! An entry is written as `{"age":42,"name":"Peter"}`, the name in the path is percent-encoded.
+//! Errors are answered with 400, 403, 404, 405 or 500 and `{"error":"<description>"}`.
+//!
+//! Inserts and erases are applied as `i` and `e` commands of the connection's session, so they
+//! are logged and conflict with open transactions like those of the line protocol. Connections are kept alive unless the client asks to close them.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+
+use super::{apply, client_session, is_local, lock, ShutdownHandle, SHUTDOWN_REFUSED};
+use crate::command::{Database, Session, DEFAULT_TREE};
+use crate::lexer;
+use crate::tree::{write_json_string, Tree};
+
+/// Longest request line or header line which is accepted
+const MAX_LINE: u64 = 8 * 1024;
+
+/// Largest request body which is accepted, the body is read and ignored
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// The parts of a request which the routes need
+struct Request {
+    method: String,
+    /// The path without the query
+    path: String,
+    /// The query parameters in their order, percent-decoded
+    query: Vec<(String, String)>,
+    /// Close the connection after the response
+    close: bool,
+}
+
+/// A response with a JSON body
+struct Response {
+    status: u16,
+    body: String,
+    /// The allowed methods, sent with 405
+    allow: Option<&'static str>,
+}
+
+/// Writes a string as a JSON string
+struct JsonString<'a>(&'a str);
+
+impl fmt::Display for JsonString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_json_string(f, self.0)
+    }
+}
+
+impl Response {
+    /// Returns a response with the given status and JSON body.
+    fn json(status: u16, body: String) -> Response {
+        Response { status, body, allow: None }
+    }
+
+    /// Returns an error response with the given status and description.
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, format!("{{\"error\":{}}}", JsonString(message)))
+    }
+
+    /// Returns a 405 response which lists the allowed methods.
+    fn not_allowed(allow: &'static str) -> Response {
+        Response { allow: Some(allow), ..Response::error(405, "method not allowed") }
+    }
+}
+
+/// Serve the requests of a single connection until the client closes it or asks to, or the server stops.
+/// Returns an error if the connection fails.
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `database` - The shared trees
+/// * `handle` - Handle of the server
+pub fn serve(stream: TcpStream, database: &Mutex<Database>, handle: &ShutdownHandle) -> io::Result<()> {
+    let local = is_local(stream.peer_addr()?.ip());
+    let mut session = client_session(&lock(database), local);
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    loop {
+        let request = match read_request(&mut reader) {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                // The rest of the stream cannot be trusted after a malformed request
+                write_response(&mut writer, &Response::error(400, &error.to_string()), true)?;
+                return writer.flush();
+            }
+            Err(error) => return Err(error),
+        };
+        let shutdown_requested = request.method == "POST" && request.path == "/shutdown";
+        let shutdown = shutdown_requested && local;
+        let response = if shutdown {
+            Response::json(200, "{\"status\":\"stopping\"}".to_string())
+        } else if shutdown_requested {
+            Response::error(403, SHUTDOWN_REFUSED)
+        } else {
+            route(&request, database, &mut session)
+        };
+        write_response(&mut writer, &response, request.close || shutdown)?;
+        writer.flush()?;
+        if shutdown {
+            handle.shutdown();
+        }
+        if request.close || shutdown {
+            return Ok(());
+        }
+    }
+}
+
+/// Read the next request, skipping its body.
+/// Returns the request, None if the client closed the connection, or an error of kind
+/// `InvalidData` if the request is malformed.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn read_request(reader: &mut dyn BufRead) -> io::Result<Option<Request>> {
+    let line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    let mut parts = line.split(' ');
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version), None) if version.starts_with("HTTP/1.") => (method, target, version),
+        _ => return Err(invalid(format!("malformed request line {}", lexer::quote(&line)))),
+    };
+    let mut close = version == "HTTP/1.0";
+    let mut length = 0;
+    loop {
+        let header = read_line(reader)?.ok_or_else(|| invalid("the request ended in the headers".to_string()))?;
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')
+            .ok_or_else(|| invalid(format!("malformed header {}", lexer::quote(&header))))?;
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value.parse().map_err(|_| invalid("invalid Content-Length".to_string()))?;
+        } else if name.eq_ignore_ascii_case("connection") {
+            close = value.eq_ignore_ascii_case("close") || (close && !value.eq_ignore_ascii_case("keep-alive"));
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(invalid("Transfer-Encoding is not supported, send a Content-Length".to_string()));
+        }
+    }
+    if length > MAX_BODY {
+        return Err(invalid(format!("the body is longer than {} bytes", MAX_BODY)));
+    }
+    io::copy(&mut reader.take(length), &mut io::sink())?;
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query.split('&').filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect::<io::Result<_>>()?;
+    Ok(Some(Request { method: method.to_string(), path: path.to_string(), query, close }))
+}
+
+/// Read a line which ends with CRLF or LF.
+/// Returns the line without the line ending, None at the end of the stream, or an error of kind
+/// `InvalidData` if the line is too long or not UTF-8.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn read_line(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader.take(MAX_LINE).read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(invalid(format!("a line of the request is longer than {} bytes", MAX_LINE)));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("the request is not UTF-8".to_string()))?;
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+/// Returns the response to a request.
+///
+/// # Arguments
+///
+/// * `request` - The request
+/// * `database` - The shared trees
+/// * `session` - The session of the connection
+fn route(request: &Request, database: &Mutex<Database>, session: &mut Session) -> Response {
+    let segments: Vec<&str> = request.path.trim_start_matches('/').split('/').collect();
+    let method = request.method.as_str();
+    match (segments.as_slice(), method) {
+        (["entries", age, name], _) => {
+            let age = match age.parse::<i32>() {
+                Ok(age) => age,
+                Err(_) => return Response::error(400, &format!("invalid age {}", age)),
+            };
+            let name = match percent_decode(name) {
+                Ok(name) => name,
+                Err(error) => return Response::error(400, &error.to_string()),
+            };
+            match method {
+                "GET" => {
+                    let found = default_tree(&lock(database)).find(age, name.clone());
+                    if found {
+                        Response::json(200, entry(age, &name))
+                    } else {
+                        Response::error(404, "the entry is not in the tree")
+                    }
+                }
+                "PUT" => change(database, session, 'i', age, &name),
+                "DELETE" => change(database, session, 'e', age, &name),
+                _ => Response::not_allowed("GET, PUT, DELETE"),
+            }
+        }
+        (["entries"], "GET") => {
+            let from = match parameter(request, "from", i32::MIN) {
+                Ok(from) => from,
+                Err(response) => return response,
+            };
+            let to = match parameter(request, "to", i32::MAX) {
+                Ok(to) => to,
+                Err(response) => return response,
+            };
+            let database = lock(database);
+            let entries: Vec<String> = default_tree(&database).range(from..=to).map(|(age, name)| entry(age, name)).collect();
+            Response::json(200, format!("[{}]", entries.join(",")))
+        }
+        (["tree"], "GET") => {
+            Response::json(200, default_tree(&lock(database)).to_string())
+        }
+        (["stats"], "GET") => {
+            Response::json(200, stats(default_tree(&lock(database))))
+        }
+        (["health"], "GET") => {
+            Response::json(200, "{\"status\":\"ok\"}".to_string())
+        }
+        (["entries"], _) | (["tree"], _) | (["stats"], _) | (["health"], _) => Response::not_allowed("GET"),
+        (["shutdown"], _) => Response::not_allowed("POST"),
+        _ => Response::error(404, &format!("no resource at {}", request.path)),
+    }
+}
+
+/// Insert or erase an entry with the `i` or `e` command.
+/// Returns the entry with 201 for an insert and 200 for an erase which changed the tree, 200 for an
+/// insert of an entry which was already there, or an error.
+///
+/// # Arguments
+///
+/// * `database` - The shared trees
+/// * `session` - The session of the connection
+/// * `command` - `i` or `e`
+/// * `age` - Age of the entry
+/// * `name` - Name of the entry
+fn change(database: &Mutex<Database>, session: &mut Session, command: char, age: i32, name: &str) -> Response {
+    let mut database = lock(database);
+    let present = default_tree(&database).find(age, name.to_string());
+    if command == 'e' && !present {
+        return Response::error(404, "the entry is not in the tree");
+    }
+    if let Err(error) = apply(&mut database, session, &format!("{} {} {}", command, age, lexer::quote(name))) {
+        return Response::error(500, &error);
+    }
+    let status = if command == 'i' && !present { 201 } else { 200 };
+    Response::json(status, entry(age, name))
+}
+
+/// Returns an integer query parameter, the default if it is missing or empty, or a 400 response if it is not an integer.
+///
+/// # Arguments
+///
+/// * `request` - The request
+/// * `key` - Name of the parameter
+/// * `default` - Value of a missing parameter
+fn parameter(request: &Request, key: &str, default: i32) -> Result<i32, Response> {
+    match request.query.iter().rev().find(|(k, _)| k == key) {
+        Some((_, value)) if !value.is_empty() => {
+            value.parse().map_err(|_| Response::error(400, &format!("invalid {} {}", key, lexer::quote(value))))
+        }
+        _ => Ok(default)
+    }
+}
+
+/// Returns the committed default tree, which always exists.
+fn default_tree(database: &Database) -> &Tree {
+    database.tree(DEFAULT_TREE).expect("the default tree exists")
+}
+
+/// Returns an entry as a JSON object.
+fn entry(age: i32, name: &str) -> String {
+    format!("{{\"age\":{},\"name\":{}}}", age, JsonString(name))
+}
+
+/// Returns the size and height of a tree as a JSON object, with the `stats` feature also the counters.
+#[cfg(not(feature = "stats"))]
+fn stats(tree: &Tree) -> String {
+    format!("{{\"size\":{},\"height\":{}}}", tree.len(), tree.height())
+}
+
+/// Returns the size and height of a tree as a JSON object, with the `stats` feature also the counters.
+#[cfg(feature = "stats")]
+fn stats(tree: &Tree) -> String {
+    let stats = tree.stats();
+    format!("{{\"size\":{},\"height\":{},\"inserts\":{},\"erases\":{},\"finds\":{},\"single_rotations\":{},\
+             \"double_rotations\":{},\"rebalances_per_insert\":{},\"max_insert_rebalances\":{},\
+             \"rebalances_per_erase\":{},\"max_erase_rebalances\":{},\"visits_per_find\":{},\
+             \"max_find_visits\":{},\"max_height\":{}}}",
+            tree.len(), tree.height(), stats.inserts, stats.erases, stats.finds, stats.single_rotations,
+            stats.double_rotations, stats.rebalances_per_insert(), stats.max_insert_rebalances,
+            stats.rebalances_per_erase(), stats.max_erase_rebalances, stats.visits_per_find(),
+            stats.max_find_visits, stats.max_height)
+}
+
+/// Write a response with its headers.
+///
+/// # Arguments
+///
+/// * `out` - The connection
+/// * `response` - The response
+/// * `close` - Tell the client that the connection is closed after the response
+fn write_response(out: &mut dyn Write, response: &Response, close: bool) -> io::Result<()> {
+    write!(out, "HTTP/1.1 {} {}\r\n", response.status, reason(response.status))?;
+    write!(out, "Content-Type: application/json\r\nContent-Length: {}\r\n", response.body.len())?;
+    if let Some(allow) = response.allow {
+        write!(out, "Allow: {}\r\n", allow)?;
+    }
+    if close {
+        write!(out, "Connection: close\r\n")?;
+    }
+    write!(out, "\r\n{}", response.body)
+}
+
+/// Returns the reason phrase of a status code.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Returns a string with the `%XX` escapes replaced by the bytes they stand for, or an error of kind
+/// `InvalidData` if an escape is malformed or the bytes are not UTF-8.
+///
+/// # Argument
+///
+/// * `s` - Part of a path or query
+fn percent_decode(s: &str) -> io::Result<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let escape = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| invalid(format!("malformed escape in {}", lexer::quote(s))))?;
+            bytes.push(escape);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid(format!("{} is not UTF-8", lexer::quote(s))))
+}
+
+/// Returns an error of kind `InvalidData` with the given description.
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
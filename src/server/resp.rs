@@ -0,0 +1,370 @@
+//! RESP2 front end of the server, which serves the sorted set commands of Redis on the trees.
+//!
+//! A key names a tree, a score is the age and a member is the name of an entry. `ZADD` creates
+//! the tree of a key which does not exist. Commands are read as RESP arrays of bulk strings, as
+//! sent by `redis-cli` and the client libraries, or as inline commands split at whitespace.
+//!
+//! | Command                                                        | Reply                                   |
+//! |----------------------------------------------------------------|-----------------------------------------|
+//! | `ZADD key score member [score member ...]`                     | number of members which were added      |
+//! | `ZREM key member [member ...]`                                 | number of members which were removed    |
+//! | `ZSCORE key member`                                            | the score, nil if the member is missing |
+//! | `ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]`  | the members with a score in the range   |
+//! | `ZRANK key member`                                             | position of the member, nil if missing  |
+//! | `ZCARD key`                                                    | number of members, 0 for a missing key  |
+//! | `PING [message]`, `QUIT`, `SHUTDOWN`, `COMMAND`                 | as in Redis, `COMMAND` returns nothing   |
+//!
+//! Unlike in Redis scores are integers, `-inf`, `+inf` and `(` for an exclusive bound are
+//! understood by `ZRANGEBYSCORE`. A member is unique in a sorted set, so `ZADD` of a member
+//! with a new score moves it. As the trees are ordered by score first, finding the score of a
+//! member takes O(n). `SHUTDOWN` is only accepted from the local machine.
+//!
+//! Changes are applied as `i` and `e` commands of the connection's session, so the `default`
+//! key is logged like a change of the line protocol.
+//! The scores of a `ZADD` are checked before any change is made, and the lock of the database is
+//! held for the whole command, so no other client sees a part of its changes.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+
+use super::{apply, client_session, is_local, lock, ShutdownHandle, SHUTDOWN_REFUSED};
+use crate::command::{Database, Session};
+use crate::lexer;
+use crate::tree::Tree;
+
+/// Longest line of a request which is accepted
+const MAX_LINE: u64 = 64 * 1024;
+
+/// Longest bulk string which is accepted
+const MAX_BULK: usize = 1024 * 1024;
+
+/// Most arguments of a command which are accepted
+const MAX_ARGUMENTS: usize = 1024 * 1024;
+
+/// A reply to a command
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Status(&'static str),
+    Error(String),
+    Integer(i64),
+    /// A bulk string, None is the nil reply
+    Bulk(Option<String>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    /// Returns the error reply for a command with the wrong number of arguments.
+    fn arity(command: &str) -> Reply {
+        Reply::Error(format!("ERR wrong number of arguments for '{}' command", command.to_ascii_lowercase()))
+    }
+
+    /// Write the reply in the RESP2 encoding.
+    fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        match self {
+            Reply::Status(status) => write!(out, "+{}\r\n", status),
+            Reply::Error(error) => write!(out, "-{}\r\n", error.replace(&['\r', '\n'][..], " ")),
+            Reply::Integer(integer) => write!(out, ":{}\r\n", integer),
+            Reply::Bulk(None) => write!(out, "$-1\r\n"),
+            Reply::Bulk(Some(bulk)) => write!(out, "${}\r\n{}\r\n", bulk.len(), bulk),
+            Reply::Array(replies) => {
+                write!(out, "*{}\r\n", replies.len())?;
+                replies.iter().try_for_each(|reply| reply.write(out))
+            }
+        }
+    }
+}
+
+/// Serve the commands of a single connection until the client quits or disconnects, or the server stops.
+/// Returns an error if the connection fails.
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `database` - The shared trees
+/// * `handle` - Handle of the server
+pub fn serve(stream: TcpStream, database: &Mutex<Database>, handle: &ShutdownHandle) -> io::Result<()> {
+    let local = is_local(stream.peer_addr()?.ip());
+    let mut session = client_session(&lock(database), local);
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    loop {
+        let arguments = match read_command(&mut reader) {
+            Ok(Some(arguments)) => arguments,
+            Ok(None) => return Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                // Like Redis, the connection is closed as the rest of the stream cannot be trusted
+                Reply::Error(format!("ERR Protocol error: {}", error)).write(&mut writer)?;
+                return writer.flush();
+            }
+            Err(error) => return Err(error),
+        };
+        let name = match arguments.first() {
+            Some(name) => name.to_ascii_uppercase(),
+            None => continue,
+        };
+        match name.as_str() {
+            "SHUTDOWN" if !local => Reply::Error(format!("ERR {}", SHUTDOWN_REFUSED)).write(&mut writer)?,
+            "QUIT" | "SHUTDOWN" => {
+                Reply::Status("OK").write(&mut writer)?;
+                writer.flush()?;
+                if name == "SHUTDOWN" {
+                    handle.shutdown();
+                }
+                return Ok(());
+            }
+            _ => execute(&name, &arguments[1..], database, &mut session).write(&mut writer)?
+        }
+        writer.flush()?;
+    }
+}
+
+/// Read the next command, as an array of bulk strings or an inline command.
+/// Returns the command name and its arguments, None if the client closed the connection, or an error
+/// of kind `InvalidData` if the request is malformed.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn read_command(reader: &mut dyn BufRead) -> io::Result<Option<Vec<String>>> {
+    let line = match read_line(reader)? {
+        Some(line) => line,
+        None => return Ok(None),
+    };
+    let count = match line.strip_prefix('*') {
+        Some(count) => length(count, MAX_ARGUMENTS)?,
+        None => return Ok(Some(line.split_whitespace().map(str::to_string).collect())),
+    };
+    let mut arguments = Vec::with_capacity(count.min(64));
+    for _ in 0..count {
+        let header = read_line(reader)?.ok_or_else(|| invalid("the command ended early".to_string()))?;
+        let size = match header.strip_prefix('$') {
+            Some(size) => length(size, MAX_BULK)?,
+            None => return Err(invalid(format!("expected '$', got {}", lexer::quote(&header)))),
+        };
+        let mut bulk = vec![0; size + 2];
+        reader.read_exact(&mut bulk)?;
+        if !bulk.ends_with(b"\r\n") {
+            return Err(invalid("a bulk string does not end with CRLF".to_string()));
+        }
+        bulk.truncate(size);
+        arguments.push(String::from_utf8(bulk).map_err(|_| invalid("an argument is not UTF-8".to_string()))?);
+    }
+    Ok(Some(arguments))
+}
+
+/// Read a line which ends with CRLF or LF.
+/// Returns the line without the line ending, None at the end of the stream, or an error of kind
+/// `InvalidData` if the line is too long or not UTF-8.
+///
+/// # Argument
+///
+/// * `reader` - The connection
+fn read_line(reader: &mut dyn BufRead) -> io::Result<Option<String>> {
+    let mut line = Vec::new();
+    if reader.take(MAX_LINE).read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return Err(invalid(format!("a line is longer than {} bytes", MAX_LINE)));
+    }
+    let line = String::from_utf8(line).map_err(|_| invalid("a line is not UTF-8".to_string()))?;
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+/// Returns the length in the header of an array or bulk string, or an error of kind `InvalidData`
+/// if it is not a number or greater than the limit.
+///
+/// # Arguments
+///
+/// * `s` - The length
+/// * `limit` - Greatest length which is accepted
+fn length(s: &str, limit: usize) -> io::Result<usize> {
+    match s.parse() {
+        Ok(length) if length <= limit => Ok(length),
+        _ => Err(invalid(format!("invalid length {}", lexer::quote(s)))),
+    }
+}
+
+/// Returns the reply to a command.
+///
+/// # Arguments
+///
+/// * `name` - Name of the command in upper case
+/// * `arguments` - The arguments after the name
+/// * `database` - The shared trees
+/// * `session` - The session of the connection
+fn execute(name: &str, arguments: &[String], database: &Mutex<Database>, session: &mut Session) -> Reply {
+    match (name, arguments) {
+        ("PING", []) => Reply::Status("PONG"),
+        ("PING", [message]) => Reply::Bulk(Some(message.clone())),
+        ("COMMAND", _) => Reply::Array(Vec::new()),
+        ("ZADD", [key, pairs @ ..]) if !pairs.is_empty() && pairs.len() % 2 == 0 => {
+            let mut entries = Vec::with_capacity(pairs.len() / 2);
+            for pair in pairs.chunks(2) {
+                match pair[0].parse::<i32>() {
+                    Ok(score) => entries.push((score, &pair[1])),
+                    Err(_) => return Reply::Error("ERR value is not an integer or out of range".to_string()),
+                }
+            }
+            let mut database = lock(database);
+            if database.tree(key).is_none() {
+                if let Err(error) = apply(&mut database, session, &format!("new {}", lexer::quote(key))) {
+                    return Reply::Error(format!("ERR {}", error));
+                }
+            }
+            let mut added = 0;
+            for (score, member) in entries {
+                let result = match score_of(database.tree(key).expect("tree of the key exists"), member) {
+                    Some(old) if old == score => Ok(()),
+                    Some(old) => change(&mut database, session, key, 'e', old, member)
+                        .and_then(|_| change(&mut database, session, key, 'i', score, member)),
+                    None => {
+                        added += 1;
+                        change(&mut database, session, key, 'i', score, member)
+                    }
+                };
+                if let Err(error) = result {
+                    return Reply::Error(format!("ERR {}", error));
+                }
+            }
+            Reply::Integer(added)
+        }
+        ("ZREM", [key, members @ ..]) if !members.is_empty() => {
+            let mut database = lock(database);
+            let mut removed = 0;
+            for member in members {
+                let score = match database.tree(key).and_then(|tree| score_of(tree, member)) {
+                    Some(score) => score,
+                    None => continue,
+                };
+                if let Err(error) = change(&mut database, session, key, 'e', score, member) {
+                    return Reply::Error(format!("ERR {}", error));
+                }
+                removed += 1;
+            }
+            Reply::Integer(removed)
+        }
+        ("ZSCORE", [key, member]) => {
+            let database = lock(database);
+            let score = database.tree(key).and_then(|tree| score_of(tree, member));
+            Reply::Bulk(score.map(|score| score.to_string()))
+        }
+        ("ZRANK", [key, member]) => {
+            let database = lock(database);
+            let rank = database.tree(key).and_then(|tree| score_of(tree, member).map(|score| tree.rank(score, member)));
+            rank.map_or(Reply::Bulk(None), |rank| Reply::Integer(rank as i64))
+        }
+        ("ZCARD", [key]) => {
+            let database = lock(database);
+            Reply::Integer(database.tree(key).map_or(0, Tree::len) as i64)
+        }
+        ("ZRANGEBYSCORE", [key, min, max, options @ ..]) => {
+            let (from, to) = match (bound(min, true), bound(max, false)) {
+                (Some(from), Some(to)) => (from, to),
+                _ => return Reply::Error("ERR min or max is not an integer".to_string()),
+            };
+            let (with_scores, offset, count) = match range_options(options) {
+                Ok(options) => options,
+                Err(reply) => return reply,
+            };
+            let database = lock(database);
+            let tree = match database.tree(key) {
+                Some(tree) if from <= to && offset >= 0 => tree,
+                _ => return Reply::Array(Vec::new()),
+            };
+            let entries = tree.range(from.max(i32::MIN as i64) as i32..=to.min(i32::MAX as i64) as i32)
+                .skip(offset as usize)
+                .take(if count < 0 { usize::MAX } else { count as usize });
+            let mut replies = Vec::new();
+            for (score, member) in entries {
+                replies.push(Reply::Bulk(Some(member.to_string())));
+                if with_scores {
+                    replies.push(Reply::Bulk(Some(score.to_string())));
+                }
+            }
+            Reply::Array(replies)
+        }
+        ("PING", _) | ("ZADD", _) | ("ZREM", _) | ("ZSCORE", _) | ("ZRANK", _) | ("ZCARD", _) | ("ZRANGEBYSCORE", _) => {
+            Reply::arity(name)
+        }
+        _ => Reply::Error(format!("ERR unknown command '{}'", name.to_ascii_lowercase())),
+    }
+}
+
+/// Insert or erase an entry of the tree of a key with the `i` or `e` command.
+/// Returns the description of the error if the command failed.
+///
+/// # Arguments
+///
+/// * `database` - The locked database
+/// * `session` - The session of the connection
+/// * `key` - Name of the tree, which exists
+/// * `command` - `i` or `e`
+/// * `score` - Age of the entry
+/// * `member` - Name of the entry
+fn change(database: &mut Database, session: &mut Session, key: &str, command: char, score: i32, member: &str) -> Result<(), String> {
+    // Addressed with `@`, so the tree in use of the session stays as it is
+    apply(database, session, &format!("@{} {} {} {}", lexer::quote(key), command, score, lexer::quote(member)))
+}
+
+/// Returns the score of a member, or None if it is not in the tree.
+///
+/// # Arguments
+///
+/// * `tree` - The tree of the key
+/// * `member` - Name of the entry
+fn score_of(tree: &Tree, member: &str) -> Option<i32> {
+    tree.iter().find(|(_, name)| *name == member).map(|(score, _)| score)
+}
+
+/// Returns the smallest or greatest score in a range given as an integer, `-inf`, `+inf` or `(<integer>`,
+/// or None if it is none of those. The result may be outside of the scores after excluding the bound.
+///
+/// # Arguments
+///
+/// * `s` - The bound
+/// * `lower` - True for the lower bound, false for the upper bound
+fn bound(s: &str, lower: bool) -> Option<i64> {
+    match s {
+        "-inf" => Some(i32::MIN as i64),
+        "+inf" | "inf" => Some(i32::MAX as i64),
+        _ => match s.strip_prefix('(') {
+            Some(exclusive) => exclusive.parse::<i32>().ok().map(|score| score as i64 + if lower { 1 } else { -1 }),
+            None => s.parse::<i32>().ok().map(i64::from),
+        }
+    }
+}
+
+/// Returns whether `WITHSCORES` was given and the offset and count of `LIMIT`, -1 for all, or an error reply.
+///
+/// # Argument
+///
+/// * `options` - The arguments of `ZRANGEBYSCORE` after the bounds
+fn range_options(options: &[String]) -> Result<(bool, i64, i64), Reply> {
+    let mut with_scores = false;
+    let (mut offset, mut count) = (0, -1);
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        if option.eq_ignore_ascii_case("WITHSCORES") {
+            with_scores = true;
+        } else if option.eq_ignore_ascii_case("LIMIT") {
+            let mut integer = || options.next().and_then(|value| value.parse::<i64>().ok());
+            match (integer(), integer()) {
+                (Some(o), Some(c)) => {
+                    offset = o;
+                    count = c;
+                }
+                _ => return Err(Reply::Error("ERR value is not an integer or out of range".to_string())),
+            }
+        } else {
+            return Err(Reply::Error("ERR syntax error".to_string()));
+        }
+    }
+    Ok((with_scores, offset, count))
+}
+
+/// Returns an error of kind `InvalidData` with the given description.
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{command_loop, command_loop_with, Database, Format, Interpreter, LineError, Options, Outcome};
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        command_loop(&mut input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_contains() {
+        assert_eq!(run("i 42 Peter\nc 42 Peter\nc 42 Mark\nc 21 Peter\n"), "y\nn\nn\n");
+    }
+
+    #[test]
+    fn test_exit() {
+        assert_eq!(run("i 42 Peter\nq\np\n"), "");
+    }
+
+    #[test]
+    fn test_invalid_commands_are_skipped() {
+        assert_eq!(run("\ni x Peter\nfoo\ni 42 Peter\np\n"), "[{\"42\":\"Peter\"},null,null]\n");
+    }
+
+    #[test]
+    fn test_trace_option() {
+        let options = Options { trace: true, ..Options::default() };
+        let mut output = Vec::new();
+        command_loop_with(&mut "i 1 a\nt off\ni 2 b\n".as_bytes(), &mut output, &options).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "insert {1: a}\n  inserted {1: a}\n  tree:\n    {1: a}\n");
+    }
+
+    /// Returns the output written before the loop stopped and the error it stopped with.
+    fn run_strict(input: &str, options: &Options) -> (String, io::Error) {
+        let mut output = Vec::new();
+        let error = command_loop_with(&mut input.as_bytes(), &mut output, options).unwrap_err();
+        (String::from_utf8(output).unwrap(), error)
+    }
+
+    #[test]
+    fn test_strict_stops_at_first_error() {
+        let options = Options { strict: true, ..Options::default() };
+        let (output, error) = run_strict("i 1 a\nc 1 a\ni x b\nc 1 a\n", &options);
+        assert_eq!(output, "y\n");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = error.get_ref().and_then(|e| e.downcast_ref::<LineError>()).unwrap();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.text, "i x b");
+        assert_eq!(error.to_string(), "line 3: `i x b`: column 3: unable to parse int (age).");
+    }
+
+    #[test]
+    fn test_check_fails_on_miss() {
+        assert_eq!(run("i 1 a\nc 2 b\n"), "n\n");
+        let options = Options { strict: true, check: true, ..Options::default() };
+        let (output, error) = run_strict("i 1 a\nc 1 a\nc 2 b\np\n", &options);
+        assert_eq!(output, "y\nn\n");
+        assert_eq!(error.to_string(), "line 3: `c 2 b`: check failed, the entry is not in the tree");
+    }
+
+    #[test]
+    fn test_inputs_share_the_tree() {
+        let mut interpreter = Interpreter::new(Options::default()).unwrap();
+        let mut output = Vec::new();
+        assert!(interpreter.run(&mut "i 2 b\ni 1 a\n".as_bytes(), &mut output).unwrap());
+        assert!(!interpreter.run(&mut "c 1 a\nq\ni 3 c\n".as_bytes(), &mut output).unwrap());
+        assert_eq!(String::from_utf8(output).unwrap(), "y\n");
+        assert_eq!(interpreter.tree().len(), 2);
+    }
+
+    #[test]
+    fn test_named_tree_errors() {
+        let options = Options { strict: true, ..Options::default() };
+        let error = |input: &str| run_strict(input, &options).1.to_string();
+        assert_eq!(error("@x i 1 a\n"), "line 1: `@x i 1 a`: no tree named x");
+        assert_eq!(error("new a\nnew a\n"), "line 2: `new a`: tree a already exists");
+        assert_eq!(error("use b\n"), "line 1: `use b`: no tree named b");
+        assert_eq!(error("drop default\n"), "line 1: `drop default`: the default tree cannot be dropped");
+        assert_eq!(error("union b\n"), "line 1: `union b`: no tree named b");
+        assert_eq!(error("@a use b\n"), "line 1: `@a use b`: column 4: use cannot be used with @<tree>");
+        assert_eq!(error("@ p\n"), "line 1: `@ p`: column 1: expected @<tree> <command>");
+        assert_eq!(error("@a\n"), "line 1: `@a`: column 3: expected a command after @a");
+    }
+
+    #[test]
+    fn test_formats() {
+        let input = "i 2 b\ni 1 \"a a\"\np\n";
+        let print = |format: Format| {
+            let mut output = Vec::new();
+            command_loop_with(&mut input.as_bytes(), &mut output, &Options { format, ..Options::default() }).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        assert_eq!(print(Format::Json), run(input));
+        assert_eq!(print(Format::SortedList), "1 \"a a\"\n2 b\n");
+        assert_eq!(print(Format::Ascii), run("i 2 b\ni 1 \"a a\"\na\n"));
+        assert_eq!(print(Format::Dot), run("i 2 b\ni 1 \"a a\"\nd\n"));
+        assert!(print(Format::Pretty).starts_with("[\n  {\"2\": \"b\"},\n"));
+        assert_eq!("sorted-list".parse(), Ok(Format::SortedList));
+        assert!("yaml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("rustsint-command-save-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut interpreter = Interpreter::new(Options::default()).unwrap();
+        interpreter.run(&mut "i 1 a\ni 2 b\n".as_bytes(), &mut Vec::new()).unwrap();
+        interpreter.save(path).unwrap();
+
+        let mut loaded = Interpreter::new(Options::default()).unwrap();
+        loaded.load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(loaded.tree().iter().collect::<Vec<_>>(), vec![(1, "a"), (2, "b")]);
+        assert!(loaded.load(path).is_err());
+    }
+
+    #[test]
+    fn test_files_disabled() {
+        let options = Options { strict: true, files: false, ..Options::default() };
+        let path = std::env::temp_dir().join(format!("rustsint-command-files-{}", std::process::id()));
+        let (_, error) = run_strict(&format!("i 1 a\nsave {}\n", path.display()), &options);
+        assert_eq!(error.to_string(), format!("line 2: `save {}`: save and load are disabled", path.display()));
+        assert!(!path.exists());
+        let (_, error) = run_strict("load tree.snapshot\n", &options);
+        assert_eq!(error.to_string(), "line 1: `load tree.snapshot`: save and load are disabled");
+    }
+
+    #[test]
+    fn test_outcome() {
+        let mut database = Database::new(Options::default()).unwrap();
+        let mut session = database.session();
+        session.report_errors_to_output();
+        let mut execute = |input: &str| {
+            let mut output = Vec::new();
+            let outcome = database.execute(&mut session, input, &mut output).unwrap();
+            (outcome, String::from_utf8(output).unwrap())
+        };
+        assert_eq!(execute("i 1 \"error: x\"\n"), (Outcome::Done, String::new()));
+        // Output which looks like an error is not one
+        assert_eq!(execute("sel 0\n"), (Outcome::Done, "1 \"error: x\"\n".to_string()));
+        let error = LineError { line: 3, text: "union x".to_string(), message: "no tree named x".to_string() };
+        assert_eq!(execute("union x\n"), (Outcome::Failed(error), "error: line 3: `union x`: no tree named x\n".to_string()));
+        assert_eq!(execute("q\n").0, Outcome::Exit);
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    use crate::differential::{run, run_bytes, Key, Op};
+
+    fn key(age: i32, name: &str) -> Key {
+        Key { age, name: name.to_string() }
+    }
+
+    #[test]
+    fn test_run() {
+        let ops = vec![
+            Op::Print,
+            Op::Insert(key(2, "b")),
+            Op::Insert(key(1, "a\"\\\n")),
+            Op::Insert(key(1, "Zoë")),
+            Op::Find(key(1, "Zoë")),
+            Op::Find(key(2, "a")),
+            Op::Print,
+            Op::Erase(key(2, "b")),
+            Op::Print,
+            Op::Reset,
+            Op::Print,
+        ];
+        assert_eq!(run(&ops), Ok(()));
+    }
+
+    #[test]
+    fn test_run_bytes() {
+        // The same kind of input the fuzz targets get
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 2000);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            if let Err(error) = run_bytes(&data) {
+                panic!("{}", error);
+            }
+        }
+    }
+}
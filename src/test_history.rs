@@ -0,0 +1,60 @@
+#[cfg(test)]
+mod tests {
+    use crate::{command_loop_with, Options};
+
+    fn run_with(input: &str, options: &Options) -> String {
+        let mut output = Vec::new();
+        command_loop_with(&mut input.as_bytes(), &mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn run(input: &str) -> String {
+        run_with(input, &Options::default())
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        assert_eq!(run("i 1 a\ni 2 b\ne 1 a\nu\np\nu\np\nr\nr\np\n"),
+                   "[{\"2\":\"b\"},[{\"1\":\"a\"},null,null],null]\n[{\"1\":\"a\"},null,null]\n[{\"2\":\"b\"},null,null]\n");
+    }
+
+    #[test]
+    fn test_undo_reset_restores_shape() {
+        let build = "i 5 a\ni 3 b\ni 8 c\ni 1 d\ni 4 e\ne 8 c\n";
+        let before = run(&format!("{}a v\n", build));
+        assert_eq!(run(&format!("{}x\nu\na v\n", build)), before);
+        assert_eq!(run(&format!("{}x\nu\nr\np\n", build)), "null\n");
+    }
+
+    #[test]
+    fn test_noop_mutations_are_not_recorded() {
+        assert_eq!(run("i 1 a\ni 1 a\ne 2 b\nhistory\nu\ns\n"), "@default i 1 a\n0\n");
+    }
+
+    #[test]
+    fn test_new_mutation_clears_redo() {
+        assert_eq!(run("i 1 a\nu\ni 2 b\nr\np\n"), "[{\"2\":\"b\"},null,null]\n");
+    }
+
+    #[test]
+    fn test_depth() {
+        let options = Options { history: 2, ..Options::default() };
+        assert_eq!(run_with("i 1 a\ni 2 b\ni 3 c\nu\nu\nu\nhistory\nsel 0\n", &options), "1 a\n");
+        let options = Options { history: 0, ..Options::default() };
+        assert_eq!(run_with("i 1 a\nx\nu\nhistory\ns\n", &options), "0\n");
+    }
+
+    #[test]
+    fn test_named_trees() {
+        let input = "new t\n@t i 1 a\ni 2 b\n@t union default\n@t x\nhistory\nu\nu\n@t p\ndrop t\nhistory\n";
+        assert_eq!(run(input), "@t i 1 a\n@default i 2 b\n@t union default\n@t x\n[{\"1\":\"a\"},null,null]\n@default i 2 b\n");
+    }
+
+    #[test]
+    fn test_quoted_tree_names() {
+        let history = run("new \"my tree\"\n@\"my tree\" i 1 a\nhistory\n");
+        assert_eq!(history, "@\"my tree\" i 1 a\n");
+        // The lines of the history can be run again
+        assert_eq!(run(&format!("new \"my tree\"\n{}@\"my tree\" p\n", history)), "[{\"1\":\"a\"},null,null]\n");
+    }
+}
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    use crate::server::{Protocol, Server};
+    use crate::{Database, Options};
+
+    fn start() -> (SocketAddr, JoinHandle<Database>) {
+        let server = Server::bind("127.0.0.1:0", Protocol::Http, Database::new(Options::default()).unwrap()).unwrap();
+        let address = server.local_addr();
+        (address, thread::spawn(move || server.run().unwrap()))
+    }
+
+    /// Send a request on its own connection, returns the status and the body
+    fn request(address: SocketAddr, method: &str, target: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", method, target).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response["HTTP/1.1 ".len()..][..3].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+
+    fn stop(address: SocketAddr, server: JoinHandle<Database>) -> Database {
+        assert_eq!(request(address, "POST", "/shutdown").0, 200);
+        server.join().unwrap()
+    }
+
+    #[test]
+    fn test_entries() {
+        let (address, server) = start();
+        assert_eq!(request(address, "PUT", "/entries/42/Peter"), (201, "{\"age\":42,\"name\":\"Peter\"}".to_string()));
+        assert_eq!(request(address, "PUT", "/entries/42/Peter").0, 200);
+        assert_eq!(request(address, "PUT", "/entries/21/Mary%20%22Ann%22"),
+                   (201, "{\"age\":21,\"name\":\"Mary \\\"Ann\\\"\"}".to_string()));
+        assert_eq!(request(address, "GET", "/entries/42/Peter"), (200, "{\"age\":42,\"name\":\"Peter\"}".to_string()));
+        assert_eq!(request(address, "GET", "/entries/42/Paul"), (404, "{\"error\":\"the entry is not in the tree\"}".to_string()));
+        assert_eq!(request(address, "GET", "/tree"),
+                   (200, "[{\"42\":\"Peter\"},[{\"21\":\"Mary \\\"Ann\\\"\"},null,null],null]".to_string()));
+        assert_eq!(request(address, "DELETE", "/entries/42/Peter").0, 200);
+        assert_eq!(request(address, "DELETE", "/entries/42/Peter").0, 404);
+        assert_eq!(request(address, "GET", "/entries/42/Peter").0, 404);
+        let database = stop(address, server);
+        assert_eq!(database.tree("default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_range() {
+        let (address, server) = start();
+        for (age, name) in &[(5, "e"), (1, "a"), (3, "c"), (3, "b"), (9, "i")] {
+            assert_eq!(request(address, "PUT", &format!("/entries/{}/{}", age, name)).0, 201);
+        }
+        assert_eq!(request(address, "GET", "/entries?from=2&to=5"),
+                   (200, "[{\"age\":3,\"name\":\"b\"},{\"age\":3,\"name\":\"c\"},{\"age\":5,\"name\":\"e\"}]".to_string()));
+        assert_eq!(request(address, "GET", "/entries?to=1").1, "[{\"age\":1,\"name\":\"a\"}]");
+        assert_eq!(request(address, "GET", "/entries?from=6&to=").1, "[{\"age\":9,\"name\":\"i\"}]");
+        assert_eq!(request(address, "GET", "/entries?from=6&to=2").1, "[]");
+        assert_eq!(request(address, "GET", "/entries").1.matches("age").count(), 5);
+        assert_eq!(request(address, "GET", "/entries?from=x"), (400, "{\"error\":\"invalid from x\"}".to_string()));
+        stop(address, server);
+    }
+
+    #[test]
+    fn test_stats_and_health() {
+        let (address, server) = start();
+        request(address, "PUT", "/entries/1/a");
+        request(address, "PUT", "/entries/2/b");
+        let (status, body) = request(address, "GET", "/stats");
+        assert_eq!(status, 200);
+        assert!(body.starts_with("{\"size\":2,\"height\":2"), "{}", body);
+        assert_eq!(request(address, "GET", "/health"), (200, "{\"status\":\"ok\"}".to_string()));
+        stop(address, server);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (address, server) = start();
+        assert_eq!(request(address, "GET", "/entries/x/a"), (400, "{\"error\":\"invalid age x\"}".to_string()));
+        assert_eq!(request(address, "GET", "/entries/1/%zz").0, 400);
+        assert_eq!(request(address, "POST", "/entries/1/a").0, 405);
+        assert_eq!(request(address, "DELETE", "/tree").0, 405);
+        assert_eq!(request(address, "GET", "/shutdown").0, 405);
+        assert_eq!(request(address, "GET", "/nothing").0, 404);
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"nonsense\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{}", response);
+        stop(address, server);
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let (address, server) = start();
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "PUT /entries/1/a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcGET /entries/1/a HTTP/1.1\r\n\r\n").unwrap();
+        write!(stream, "GET /health HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response.matches("HTTP/1.1 ").count(), 3);
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+        assert!(response.ends_with("Connection: close\r\n\r\n{\"status\":\"ok\"}"));
+        stop(address, server);
+    }
+}
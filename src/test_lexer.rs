@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use crate::lexer::{quote, tokenize, LexError, Token};
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line).unwrap().into_iter().map(|token| token.text).collect()
+    }
+
+    fn error(line: &str) -> LexError {
+        tokenize(line).unwrap_err()
+    }
+
+    #[test]
+    fn test_bare_words() {
+        assert_eq!(words("i 42 Peter\n"), vec!["i", "42", "Peter"]);
+        assert_eq!(words("  i\t42   Peter  "), vec!["i", "42", "Peter"]);
+        // Quotes and backslashes inside bare words are kept as they are
+        assert_eq!(words(r#"i 25 a"b\c"#), vec!["i", "25", r#"a"b\c"#]);
+        assert!(words("\n").is_empty());
+    }
+
+    #[test]
+    fn test_quoted() {
+        assert_eq!(tokenize(r#"i 1 "Mary Ann""#).unwrap()[2],
+                   Token { text: "Mary Ann".to_string(), column: 5, quoted: true });
+        assert_eq!(words(r#""a\"b" "c\\d" "e\nf" "g\th" """#), vec!["a\"b", "c\\d", "e\nf", "g\th", ""]);
+        assert_eq!(words(r#""\u{41}\u{e9}\u{1F600}""#), vec!["Aé😀"]);
+        assert_eq!(words("\"名前 Zoë\" x"), vec!["名前 Zoë", "x"]);
+    }
+
+    #[test]
+    fn test_quoted_target() {
+        assert_eq!(tokenize(r#"@"a b" p"#).unwrap()[0], Token { text: "@a b".to_string(), column: 1, quoted: false });
+        assert_eq!(words(r#"@"\"x" i 1 @"y""#), vec!["@\"x", "i", "1", "@y"]);
+        assert_eq!(words(r#"@ "a""#), vec!["@", "a"]);
+        assert_eq!(error(r#"@"a"b p"#).to_string(), "column 5: expected whitespace after closing quote");
+    }
+
+    #[test]
+    fn test_columns() {
+        let columns: Vec<usize> = tokenize("i  \"名前\" x").unwrap().iter().map(|token| token.column).collect();
+        assert_eq!(columns, vec![1, 4, 9]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(error(r#"i 1 "Mary"#), LexError { column: 5, message: "unterminated string".to_string() });
+        assert_eq!(error(r#"i 1 "Mary\"#).to_string(), "column 5: unterminated string");
+        assert_eq!(error(r#"i 1 "a\qb""#).to_string(), r"column 7: unknown escape \q");
+        assert_eq!(error(r#"i 1 "a"b"#).to_string(), "column 8: expected whitespace after closing quote");
+        assert_eq!(error(r#""\u41""#).to_string(), r"column 2: expected { after \u");
+        assert_eq!(error(r#""\u{}""#).to_string(), r"column 2: expected 1 to 6 hex digits and } in \u{..}");
+        assert_eq!(error(r#""\u{1234567}""#).to_string(), r"column 2: expected 1 to 6 hex digits and } in \u{..}");
+        assert_eq!(error(r#""\u{zz}""#).to_string(), r"column 2: expected 1 to 6 hex digits and } in \u{..}");
+        assert_eq!(error(r#""\u{d800}""#).to_string(), r"column 2: \u{d800} is not a unicode scalar value");
+        assert_eq!(error("é \"x").column, 3);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("Peter"), "Peter");
+        assert_eq!(quote(r#"a"b\c"#), r#"a"b\c"#);
+        assert_eq!(quote("Mary Ann"), r#""Mary Ann""#);
+        assert_eq!(quote(""), r#""""#);
+        assert_eq!(quote(r#""x"#), r#""\"x""#);
+        assert_eq!(quote(r#"@"x"#), r#""@\"x""#);
+        assert_eq!(quote("@x"), "@x");
+        assert_eq!(quote("a\\b\n\u{1}"), r#""a\\b\n\u{1}""#);
+    }
+
+    proptest! {
+        #[test]
+        fn test_quote_round_trip(name in any::<String>()) {
+            let tokens = tokenize(&format!("i 1 {}", quote(&name))).unwrap();
+            prop_assert_eq!(tokens.len(), 3);
+            prop_assert_eq!(&tokens[2].text, &name);
+        }
+    }
+}
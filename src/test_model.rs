@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use proptest::prelude::*;
+
+    use crate::Tree;
+
+    /// An operation on the tree and the model
+    #[derive(Clone, Debug)]
+    enum Op {
+        Insert(i32, String),
+        Erase(i32, String),
+        Find(i32, String),
+        Reset,
+    }
+
+    /// Keys from a small range, so operations often hit existing data and ages are shared by several names
+    fn small_key() -> impl Strategy<Value = (i32, String)> + Clone {
+        (-10..10i32, "[a-c]{0,2}")
+    }
+
+    /// Keys from the full range of ages and arbitrary names
+    fn any_key() -> impl Strategy<Value = (i32, String)> + Clone {
+        (any::<i32>(), ".{0,8}")
+    }
+
+    fn op(key: impl Strategy<Value = (i32, String)> + Clone) -> impl Strategy<Value = Op> {
+        prop_oneof![
+            6 => key.clone().prop_map(|(age, name)| Op::Insert(age, name)),
+            3 => key.clone().prop_map(|(age, name)| Op::Erase(age, name)),
+            3 => key.prop_map(|(age, name)| Op::Find(age, name)),
+            1 => Just(Op::Reset),
+        ]
+    }
+
+    /// Apply the operations to both the tree and a `BTreeSet`, and compare them after every step
+    fn check_against_model(ops: Vec<Op>) -> Result<(), TestCaseError> {
+        let mut tree = Tree::new();
+        let mut model = BTreeSet::new();
+        for (step, op) in ops.into_iter().enumerate() {
+            match op {
+                Op::Insert(age, name) => {
+                    tree.insert(age, name.clone());
+                    model.insert((age, name));
+                }
+                Op::Erase(age, name) => {
+                    tree.erase(age, name.clone());
+                    model.remove(&(age, name));
+                }
+                Op::Find(age, name) => {
+                    let expected = model.contains(&(age, name.clone()));
+                    prop_assert_eq!(tree.find(age, name), expected, "find differs at step {}", step);
+                }
+                Op::Reset => {
+                    tree.delete();
+                    tree = Tree::new();
+                    model.clear();
+                }
+            }
+            if let Err(error) = tree.validate() {
+                return Err(TestCaseError::fail(format!("invalid tree at step {}: {}", step, error)));
+            }
+            let contents: Vec<(i32, &str)> = tree.iter().collect();
+            let expected: Vec<(i32, &str)> = model.iter().map(|(age, name)| (*age, name.as_str())).collect();
+            prop_assert_eq!(contents, expected, "contents differ at step {}", step);
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_model_small_keys(ops in prop::collection::vec(op(small_key()), 0..300)) {
+            check_against_model(ops)?;
+        }
+
+        #[test]
+        fn test_model_any_keys(ops in prop::collection::vec(op(any_key()), 0..100)) {
+            check_against_model(ops)?;
+        }
+
+        #[test]
+        fn test_model_sorted_inserts(start in any::<i16>(), len in 0..200usize, descending in any::<bool>()) {
+            // Sorted input is the worst case for an unbalanced tree and needs many rotations
+            let mut ops: Vec<Op> = (0..len as i32)
+                .map(|i| Op::Insert(i32::from(start) + if descending { -i } else { i }, "Name".to_string()))
+                .collect();
+            ops.extend((0..len as i32).step_by(2).map(|i| Op::Erase(i32::from(start) + if descending { -i } else { i }, "Name".to_string())));
+            check_against_model(ops)?;
+        }
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use crate::workload::{self, Op};
+    use crate::tree::{Node, Rotation};
+    use crate::{Tree, TreeObserver};
+
+    /// Observer which records every change as a line of text
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl TreeObserver for Log {
+        fn node_inserted(&mut self, key: (i32, &str)) {
+            self.0.push(format!("inserted {:?}", key));
+        }
+
+        fn node_removed(&mut self, key: (i32, &str)) {
+            self.0.push(format!("removed {:?}", key));
+        }
+
+        fn successor_swapped(&mut self, key: (i32, &str), successor: (i32, &str)) {
+            self.0.push(format!("swapped {:?} {:?}", key, successor));
+        }
+
+        fn rotated_left(&mut self, node: (i32, &str), child: (i32, &str), _subtree: &Node) {
+            self.0.push(format!("left {:?} {:?}", node, child));
+        }
+
+        fn rotated_right(&mut self, node: (i32, &str), child: (i32, &str), _subtree: &Node) {
+            self.0.push(format!("right {:?} {:?}", node, child));
+        }
+    }
+
+    fn insert(tree: &mut Tree, age: i32, name: &str) -> Vec<String> {
+        let mut log = Log::default();
+        tree.insert_with(age, name.to_string(), &mut log);
+        log.0
+    }
+
+    fn erase(tree: &mut Tree, age: i32, name: &str) -> Vec<String> {
+        let mut log = Log::default();
+        tree.erase_with(age, name.to_string(), &mut log);
+        log.0
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut tree = Tree::new();
+        assert_eq!(insert(&mut tree, 1, "a"), vec![r#"inserted (1, "a")"#]);
+        assert_eq!(insert(&mut tree, 2, "b"), vec![r#"inserted (2, "b")"#]);
+        assert_eq!(insert(&mut tree, 3, "c"), vec![r#"inserted (3, "c")"#, r#"left (1, "a") (2, "b")"#]);
+        // Already present: nothing changes
+        assert!(insert(&mut tree, 3, "c").is_empty());
+        insert(&mut tree, 5, "e");
+        // Double rotation: right around 5, then left around 3
+        assert_eq!(insert(&mut tree, 4, "d"),
+                   vec![r#"inserted (4, "d")"#, r#"right (5, "e") (4, "d")"#, r#"left (3, "c") (4, "d")"#]);
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut tree = Tree::new();
+        for (age, name) in &[(2, "b"), (1, "a"), (4, "d"), (3, "c"), (5, "e")] {
+            insert(&mut tree, *age, name);
+        }
+        assert!(erase(&mut tree, 9, "z").is_empty());
+        // Two children: the node takes the data of its successor, whose node is removed
+        assert_eq!(erase(&mut tree, 4, "d"), vec![r#"swapped (4, "d") (5, "e")"#, r#"removed (5, "e")"#]);
+        assert_eq!(erase(&mut tree, 1, "a"),
+                   vec![r#"removed (1, "a")"#, r#"right (5, "e") (3, "c")"#, r#"left (2, "b") (3, "c")"#]);
+        assert_eq!(tree.to_string(), r#"[{"3":"c"},[{"2":"b"},null,null],[{"5":"e"},null,null]]"#);
+    }
+
+    #[test]
+    fn test_node_count() {
+        /// Counts the nodes in the tree from the reported changes
+        #[derive(Default)]
+        struct Count(usize);
+
+        impl TreeObserver for Count {
+            fn node_inserted(&mut self, _key: (i32, &str)) {
+                self.0 += 1;
+            }
+
+            fn node_removed(&mut self, _key: (i32, &str)) {
+                self.0 -= 1;
+            }
+        }
+
+        let mut tree = Tree::new();
+        let mut count = Count::default();
+        let keys = workload::random(200, 5);
+        for op in workload::mixed(&keys, 10000, 5) {
+            match op {
+                Op::Insert(age, name) => tree.insert_with(age, name, &mut count),
+                Op::Erase(age, name) => tree.erase_with(age, name, &mut count),
+                Op::Find(..) => {}
+            }
+            assert_eq!(count.0, tree.iter().count());
+        }
+    }
+
+    #[test]
+    fn test_dyn_observer() {
+        let mut tree = Tree::new();
+        let mut log = Log::default();
+        {
+            let observer: &mut dyn TreeObserver = &mut log;
+            tree.insert_with(1, "a".to_string(), observer);
+            tree.erase_with(1, "a".to_string(), observer);
+        }
+        assert_eq!(log.0, vec![r#"inserted (1, "a")"#, r#"removed (1, "a")"#]);
+    }
+
+    #[test]
+    fn test_rebalanced() {
+        /// Records the repaired cases with their pivot and the root of the repaired subtree
+        #[derive(Default)]
+        struct Cases(Vec<(Rotation, i32, i32)>);
+
+        impl TreeObserver for Cases {
+            fn rebalanced(&mut self, rotation: Rotation, pivot: (i32, &str), subtree: &Node) {
+                self.0.push((rotation, pivot.0, *subtree.age()));
+            }
+        }
+
+        let mut cases = Cases::default();
+        for ages in &[[1, 2, 3], [3, 2, 1], [3, 1, 2], [1, 3, 2]] {
+            let mut tree = Tree::new();
+            for age in ages {
+                tree.insert_with(*age, String::new(), &mut cases);
+            }
+        }
+        assert_eq!(cases.0, vec![(Rotation::RightRight, 1, 2), (Rotation::LeftLeft, 3, 2),
+                                 (Rotation::LeftRight, 3, 2), (Rotation::RightLeft, 1, 2)]);
+        assert_eq!(cases.0.iter().map(|case| case.0.to_string()).collect::<Vec<_>>(), vec!["RR", "LL", "LR", "RL"]);
+    }
+
+    #[test]
+    fn test_rotated_subtree() {
+        /// Records the root of every rotated subtree and its entries
+        #[derive(Default)]
+        struct Subtrees(Vec<String>);
+
+        impl TreeObserver for Subtrees {
+            fn rotated_left(&mut self, _node: (i32, &str), child: (i32, &str), subtree: &Node) {
+                self.0.push(format!("{} {}", child.0, subtree));
+            }
+
+            fn rotated_right(&mut self, _node: (i32, &str), child: (i32, &str), subtree: &Node) {
+                self.0.push(format!("{} {}", child.0, subtree));
+            }
+        }
+
+        let mut tree = Tree::new();
+        insert(&mut tree, 3, "c");
+        insert(&mut tree, 1, "a");
+        let mut subtrees = Subtrees::default();
+        tree.insert_with(2, "b".to_string(), &mut subtrees);
+        // Both rotations of the double rotation are reported with the subtree after them
+        assert_eq!(subtrees.0, vec![r#"2 [{"2":"b"},[{"1":"a"},null,null],null]"#,
+                                    r#"2 [{"2":"b"},[{"1":"a"},null,null],[{"3":"c"},null,null]]"#]);
+    }
+
+    #[test]
+    fn test_visited() {
+        /// Records the visited ages
+        #[derive(Default)]
+        struct Path(Vec<i32>);
+
+        impl TreeObserver for Path {
+            fn node_visited(&mut self, key: (i32, &str)) {
+                self.0.push(key.0);
+            }
+        }
+
+        let mut tree = Tree::from_sorted((1..=7).map(|age| (age, String::new())).collect());
+        let mut path = Path::default();
+        tree.insert_with(6, "a".to_string(), &mut path);
+        assert_eq!(path.0, vec![4, 6, 7]);
+        path.0.clear();
+        tree.erase_with(3, String::new(), &mut path);
+        assert_eq!(path.0, vec![4, 2, 3]);
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use crate::workload::{self, Op};
+    use crate::Tree;
+
+    /// Check every query against a sorted list of the entries of the tree
+    fn check_queries(tree: &Tree, probes: &[(i32, String)]) {
+        let entries: Vec<(i32, &str)> = tree.iter().collect();
+        assert_eq!(tree.len(), entries.len());
+        assert_eq!(tree.is_empty(), entries.is_empty());
+        assert_eq!(tree.min(), entries.first().cloned());
+        assert_eq!(tree.max(), entries.last().cloned());
+        for (k, entry) in entries.iter().enumerate() {
+            assert_eq!(tree.select(k), Some(*entry));
+            assert_eq!(tree.rank(entry.0, entry.1), k);
+        }
+        assert_eq!(tree.select(entries.len()), None);
+        for (age, name) in probes {
+            let probe = (*age, name.as_str());
+            let rank = entries.iter().filter(|entry| **entry < probe).count();
+            assert_eq!(tree.rank(*age, name), rank);
+            assert_eq!(tree.successor(*age, name), entries.iter().find(|entry| **entry > probe).cloned());
+            assert_eq!(tree.predecessor(*age, name), entries.iter().rev().find(|entry| **entry < probe).cloned());
+            let to = age.saturating_add(i32::MAX / 8);
+            let range: Vec<(i32, &str)> = entries.iter().filter(|entry| (*age..=to).contains(&entry.0)).cloned().collect();
+            assert_eq!(tree.range(*age..=to).collect::<Vec<_>>(), range);
+        }
+        assert_eq!(tree.range(i32::MIN..=i32::MAX).collect::<Vec<_>>(), entries);
+    }
+
+    #[test]
+    fn test_empty() {
+        let tree = Tree::new();
+        assert_eq!(tree.len(), 0);
+        assert_eq!(tree.height(), 0);
+        assert_eq!(tree.min(), None);
+        assert_eq!(tree.select(0), None);
+        assert_eq!(tree.rank(1, "a"), 0);
+        assert_eq!(tree.successor(1, "a"), None);
+        assert_eq!(tree.predecessor(1, "a"), None);
+        assert_eq!(tree.range(0..=10).next(), None);
+    }
+
+    #[test]
+    fn test_against_sorted_list() {
+        let keys = workload::random(60, 11);
+        let mut probes = keys.clone();
+        probes.extend(workload::misses(20, 11));
+        let mut tree = Tree::new();
+        for (step, op) in workload::mixed(&keys, 2000, 11).into_iter().enumerate() {
+            match op {
+                Op::Insert(age, name) => tree.insert(age, name),
+                Op::Erase(age, name) => tree.erase(age, name),
+                Op::Find(..) => continue,
+            }
+            tree.validate().unwrap();
+            if step % 50 == 0 {
+                check_queries(&tree, &probes);
+            }
+        }
+        check_queries(&tree, &probes);
+    }
+
+    #[test]
+    fn test_from_sorted() {
+        let tree = Tree::from_sorted(workload::sequential(100));
+        tree.validate().unwrap();
+        assert_eq!(tree.height(), 7);
+        check_queries(&tree, &workload::misses(20, 3));
+    }
+}
@@ -0,0 +1,143 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    use crate::server::{Protocol, Server};
+    use crate::{Database, Options};
+
+    /// A client which sends commands as arrays of bulk strings and reads the raw replies
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(address: SocketAddr) -> Client {
+            let stream = TcpStream::connect(address).unwrap();
+            stream.set_nodelay(true).unwrap();
+            Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream }
+        }
+
+        fn send(&mut self, arguments: &[&str]) -> String {
+            let mut request = format!("*{}\r\n", arguments.len());
+            for argument in arguments {
+                request += &format!("${}\r\n{}\r\n", argument.len(), argument);
+            }
+            self.writer.write_all(request.as_bytes()).unwrap();
+            self.reply()
+        }
+
+        /// Read a whole reply, the lines of arrays and bulk strings included
+        fn reply(&mut self) -> String {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            let count: i64 = line[1..].trim_end().parse().unwrap_or(0);
+            match line.as_bytes()[0] {
+                b'$' if count >= 0 => {
+                    let mut bulk = vec![0; count as usize + 2];
+                    self.reader.read_exact(&mut bulk).unwrap();
+                    line + &String::from_utf8(bulk).unwrap()
+                }
+                b'*' => (0..count).fold(line, |reply, _| reply + &self.reply()),
+                _ => line
+            }
+        }
+    }
+
+    fn start() -> (SocketAddr, JoinHandle<Database>) {
+        let server = Server::bind("127.0.0.1:0", Protocol::Resp, Database::new(Options::default()).unwrap()).unwrap();
+        let address = server.local_addr();
+        (address, thread::spawn(move || server.run().unwrap()))
+    }
+
+    fn stop(address: SocketAddr, server: JoinHandle<Database>) -> Database {
+        assert_eq!(Client::connect(address).send(&["SHUTDOWN"]), "+OK\r\n");
+        server.join().unwrap()
+    }
+
+    #[test]
+    fn test_sorted_set() {
+        let (address, server) = start();
+        let mut client = Client::connect(address);
+        assert_eq!(client.send(&["PING"]), "+PONG\r\n");
+        assert_eq!(client.send(&["zadd", "people", "42", "Peter", "21", "Joanna", "30", "Mary Ann"]), ":3\r\n");
+        assert_eq!(client.send(&["ZADD", "people", "42", "Peter", "25", "Joanna"]), ":0\r\n");
+        assert_eq!(client.send(&["ZCARD", "people"]), ":3\r\n");
+        assert_eq!(client.send(&["ZSCORE", "people", "Joanna"]), "$2\r\n25\r\n");
+        assert_eq!(client.send(&["ZSCORE", "people", "Paul"]), "$-1\r\n");
+        assert_eq!(client.send(&["ZRANK", "people", "Mary Ann"]), ":1\r\n");
+        assert_eq!(client.send(&["ZRANK", "nobody", "Mary Ann"]), "$-1\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "people", "-inf", "+inf"]),
+                   "*3\r\n$6\r\nJoanna\r\n$8\r\nMary Ann\r\n$5\r\nPeter\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "people", "(25", "42", "WITHSCORES"]),
+                   "*4\r\n$8\r\nMary Ann\r\n$2\r\n30\r\n$5\r\nPeter\r\n$2\r\n42\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "people", "0", "100", "LIMIT", "1", "1"]), "*1\r\n$8\r\nMary Ann\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "people", "(42", "+inf"]), "*0\r\n");
+        assert_eq!(client.send(&["ZREM", "people", "Peter", "Paul"]), ":1\r\n");
+        assert_eq!(client.send(&["ZCARD", "people"]), ":2\r\n");
+        assert_eq!(client.send(&["ZCARD", "nobody"]), ":0\r\n");
+        let database = stop(address, server);
+        let people: Vec<_> = database.tree("people").unwrap().iter().map(|(age, name)| (age, name.to_string())).collect();
+        assert_eq!(people, vec![(25, "Joanna".to_string()), (30, "Mary Ann".to_string())]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (address, server) = start();
+        let mut client = Client::connect(address);
+        assert_eq!(client.send(&["ZADD", "k", "1.5", "a"]), "-ERR value is not an integer or out of range\r\n");
+        assert_eq!(client.send(&["ZADD", "k", "1", "a", "x", "b"]), "-ERR value is not an integer or out of range\r\n");
+        assert_eq!(client.send(&["ZADD", "k", "1"]), "-ERR wrong number of arguments for 'zadd' command\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "k", "a", "1"]), "-ERR min or max is not an integer\r\n");
+        assert_eq!(client.send(&["ZRANGEBYSCORE", "k", "0", "1", "LIMIT"]), "-ERR value is not an integer or out of range\r\n");
+        assert_eq!(client.send(&["GET", "k"]), "-ERR unknown command 'get'\r\n");
+        assert_eq!(client.send(&["ZCARD", "k"]), ":0\r\n");
+
+        // An inline command, then a malformed request which closes the connection
+        client.writer.write_all(b"ZCARD k\r\n*1\r\n+x\r\n").unwrap();
+        assert_eq!(client.reply(), ":0\r\n");
+        assert_eq!(client.reply(), "-ERR Protocol error: expected '$', got +x\r\n");
+        let mut rest = String::new();
+        client.reader.read_to_string(&mut rest).unwrap();
+        assert_eq!(rest, "");
+        stop(address, server);
+    }
+
+    #[test]
+    fn test_keys_which_need_quoting() {
+        let (address, server) = start();
+        let mut client = Client::connect(address);
+        for key in ["my set", "\"x", "@\"y", "a\\b"] {
+            assert_eq!(client.send(&["ZADD", key, "1", "a", "2", "b"]), ":2\r\n", "{}", key);
+            assert_eq!(client.send(&["ZREM", key, "a"]), ":1\r\n", "{}", key);
+        }
+        let database = stop(address, server);
+        for key in ["my set", "\"x", "@\"y", "a\\b"] {
+            assert_eq!(database.tree(key).unwrap().iter().collect::<Vec<_>>(), vec![(2, "b")], "{}", key);
+        }
+        assert_eq!(database.tree("default").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_repeated_member() {
+        let (address, server) = start();
+        let mut client = Client::connect(address);
+        assert_eq!(client.send(&["ZADD", "k", "1", "a", "2", "a"]), ":1\r\n");
+        assert_eq!(client.send(&["ZREM", "k", "a", "a"]), ":1\r\n");
+        assert_eq!(client.send(&["ZADD", "k", "3", "b"]), ":1\r\n");
+        let database = stop(address, server);
+        assert_eq!(database.tree("k").unwrap().iter().collect::<Vec<_>>(), vec![(3, "b")]);
+    }
+
+    #[test]
+    fn test_default_key_is_the_default_tree() {
+        let (address, server) = start();
+        let mut client = Client::connect(address);
+        assert_eq!(client.send(&["ZADD", "default", "1", "a"]), ":1\r\n");
+        assert_eq!(client.send(&["QUIT"]), "+OK\r\n");
+        let database = stop(address, server);
+        assert!(database.tree("default").unwrap().find(1, "a".to_string()));
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use crate::Tree;
+    use crate::tree::serde_impl::{Shape, MAX_DEPTH};
+
+    /* The constructed AVL Tree would be
+         *                 5
+         *         /               \
+         *        1                 42
+         *         \              /    \
+         *          2            21     83
+         */
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::new();
+        tree.insert(42, "Peter".to_string());
+        tree.insert(21, "Joanna".to_string());
+        tree.insert(83, "Margaretha".to_string());
+        tree.insert(5, "Frank".to_string());
+        tree.insert(1, "Peter".to_string());
+        tree.insert(2, "Mark".to_string());
+        tree
+    }
+
+    fn entries(tree: &Tree) -> Vec<(i32, String)> {
+        tree.iter().map(|(age, name)| (age, name.to_string())).collect()
+    }
+
+    #[test]
+    fn test_json_sorted() {
+        let tree = sample_tree();
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(json, r#"[[1,"Peter"],[2,"Mark"],[5,"Frank"],[21,"Joanna"],[42,"Peter"],[83,"Margaretha"]]"#);
+
+        let loaded: Tree = serde_json::from_str(&json).unwrap();
+        assert_eq!(entries(&loaded), entries(&tree));
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let tree = sample_tree();
+        let json = serde_json::to_string(&Shape(tree)).unwrap();
+        assert_eq!(json, concat!(r#"[{"5":"Frank"},[{"1":"Peter"},null,[{"2":"Mark"},null,null]],"#,
+                                 r#"[{"42":"Peter"},[{"21":"Joanna"},null,null],[{"83":"Margaretha"},null,null]]]"#));
+
+        let loaded: Shape = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), json);
+
+        // The plain tree accepts the shape form as well and keeps the structure
+        let loaded: Tree = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&Shape(loaded)).unwrap(), json);
+    }
+
+    #[test]
+    fn test_json_empty() {
+        assert_eq!(serde_json::to_string(&Tree::new()).unwrap(), "[]");
+        assert_eq!(serde_json::to_string(&Shape(Tree::new())).unwrap(), "null");
+
+        assert!(serde_json::from_str::<Tree>("[]").unwrap().iter().next().is_none());
+        assert!(serde_json::from_str::<Tree>("null").unwrap().iter().next().is_none());
+        assert!(serde_json::from_str::<Shape>("null").unwrap().0.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_json_rejects_unsorted() {
+        assert!(serde_json::from_str::<Tree>(r#"[[2,"b"],[1,"a"]]"#).is_err());
+        assert!(serde_json::from_str::<Tree>(r#"[[1,"a"],[1,"a"]]"#).is_err());
+        assert!(serde_json::from_str::<Tree>(r#"[{"2":"b"},[{"3":"a"},null,null],null]"#).is_err());
+        assert!(serde_json::from_str::<Shape>(r#"[{"2":"b"},null,[{"1":"a"},null,null]]"#).is_err());
+    }
+
+    #[test]
+    fn test_json_rejects_unbalanced() {
+        let json = r#"[{"1":"a"},null,[{"2":"b"},null,[{"3":"c"},null,null]]]"#;
+        let error = serde_json::from_str::<Shape>(json).err().unwrap();
+        assert!(error.to_string().starts_with(r#"node {1: "a"} has balance factor -2"#), "{}", error);
+        assert!(serde_json::from_str::<Tree>(json).is_err());
+    }
+
+    #[test]
+    fn test_json_rejects_malformed() {
+        assert!(serde_json::from_str::<Tree>(r#"[{"1":"a","2":"b"},null,null]"#).is_err());
+        assert!(serde_json::from_str::<Tree>(r#"[{"1":"a"},null]"#).is_err());
+        assert!(serde_json::from_str::<Tree>(r#"[[1,"a",2]]"#).is_err());
+        assert!(serde_json::from_str::<Tree>(r#"[{"x":"a"},null,null]"#).is_err());
+    }
+
+    #[test]
+    fn test_bincode() {
+        let tree = sample_tree();
+
+        let bytes = bincode::serialize(&tree).unwrap();
+        let loaded: Tree = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(entries(&loaded), entries(&tree));
+
+        let bytes = bincode::serialize(&Shape(tree)).unwrap();
+        let loaded: Shape = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&Shape(sample_tree())).unwrap());
+    }
+
+    #[test]
+    fn test_msgpack() {
+        let tree = sample_tree();
+
+        let bytes = rmp_serde::to_vec(&tree).unwrap();
+        let loaded: Tree = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(entries(&loaded), entries(&tree));
+
+        let bytes = rmp_serde::to_vec(&Shape(tree)).unwrap();
+        let loaded: Shape = rmp_serde::from_slice(&bytes).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&Shape(sample_tree())).unwrap());
+    }
+
+    #[test]
+    fn test_sorted_load_is_balanced() {
+        let entries: Vec<(i32, String)> = (0..1000).map(|age| (age, "Name".to_string())).collect();
+        let json = serde_json::to_string(&entries).unwrap();
+        let tree: Tree = serde_json::from_str(&json).unwrap();
+
+        // Loading the shape form checks the balance of every node
+        let shape = serde_json::to_string(&Shape(tree)).unwrap();
+        assert!(serde_json::from_str::<Shape>(&shape).is_ok());
+    }
+
+    #[test]
+    fn test_shape_depth_limit() {
+        // bincode encoding of `depth` nested left children: `Some`, the map with one entry, `Some`
+        // for the left child and finally `None` twice for each node
+        let nested = |depth: usize| {
+            let mut bytes = Vec::new();
+            for age in 0..depth as i32 {
+                bytes.push(1u8);
+                bytes.extend_from_slice(&1u64.to_le_bytes());
+                bytes.extend_from_slice(&(depth as i32 - age).to_le_bytes());
+                bytes.extend_from_slice(&1u64.to_le_bytes());
+                bytes.push(b'a');
+            }
+            bytes.resize(bytes.len() + depth + 1, 0);
+            bytes
+        };
+        // A chain of three is unbalanced, but nested deep enough to be read
+        let error = bincode::deserialize::<Shape>(&nested(3)).err().unwrap();
+        assert!(error.to_string().contains("balance factor"), "{}", error);
+
+        let error = bincode::deserialize::<Shape>(&nested(MAX_DEPTH + 1)).err().unwrap();
+        assert_eq!(error.to_string(), format!("the tree is nested deeper than {} nodes", MAX_DEPTH));
+        assert!(bincode::deserialize::<Shape>(&nested(1_000_000)).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread::{self, JoinHandle};
+
+    use crate::server::{client_session, is_local, Protocol, Server};
+    use crate::{Database, Options};
+
+    /// A client which sends commands and reads one response per command
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(address: SocketAddr) -> Client {
+            let stream = TcpStream::connect(address).unwrap();
+            stream.set_nodelay(true).unwrap();
+            Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream }
+        }
+
+        fn send(&mut self, command: &str) -> String {
+            writeln!(self.writer, "{}", command).unwrap();
+            let mut response = String::new();
+            self.reader.read_line(&mut response).unwrap();
+            response.trim_end_matches('\n').to_string()
+        }
+
+        /// Returns true if the server closed the connection
+        fn is_closed(&mut self) -> bool {
+            let mut rest = String::new();
+            self.reader.read_line(&mut rest).unwrap() == 0
+        }
+    }
+
+    fn start(options: Options) -> (SocketAddr, JoinHandle<Database>) {
+        let server = Server::bind("127.0.0.1:0", Protocol::Lines, Database::new(options).unwrap()).unwrap();
+        let address = server.local_addr();
+        (address, thread::spawn(move || server.run().unwrap()))
+    }
+
+    #[test]
+    fn test_responses() {
+        let (address, server) = start(Options::default());
+        let mut client = Client::connect(address);
+        assert_eq!(client.send("i 5 a"), "ok");
+        assert_eq!(client.send("i 3 \"b c\""), "ok");
+        assert_eq!(client.send("c 5 a"), "y");
+        assert_eq!(client.send("c 4 a"), "n");
+        assert_eq!(client.send("min"), "3 \"b c\"");
+        assert_eq!(client.send("p"), "[{\"5\":\"a\"},[{\"3\":\"b c\"},null,null],null]");
+        assert_eq!(client.send("a"), "\"{5: a}\\n├── {3: b c}\\n└── ·\"");
+        assert_eq!(client.send("i x a"), "error: line 8: `i x a`: column 3: unable to parse int (age).");
+        assert_eq!(client.send("s"), "2");
+        client.send("q");
+        assert!(client.is_closed());
+        assert_eq!(Client::connect(address).send("shutdown"), "ok");
+        assert_eq!(server.join().unwrap().tree("default").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_clients_share_the_trees() {
+        let (address, server) = start(Options::default());
+        let clients: Vec<_> = (0..8).map(|client| thread::spawn(move || {
+            let mut connection = Client::connect(address);
+            for age in 0..50 {
+                assert_eq!(connection.send(&format!("i {} c{}", age, client)), "ok");
+            }
+        })).collect();
+        for client in clients {
+            client.join().unwrap();
+        }
+        let mut client = Client::connect(address);
+        assert_eq!(client.send("s"), "400");
+        client.send("shutdown");
+        let database = server.join().unwrap();
+        database.tree("default").unwrap().validate().unwrap();
+    }
+
+    #[test]
+    fn test_sessions() {
+        let (address, server) = start(Options::default());
+        let mut first = Client::connect(address);
+        let mut second = Client::connect(address);
+        assert_eq!(first.send("new other"), "ok");
+        assert_eq!(first.send("use other"), "ok");
+        assert_eq!(first.send("i 1 a"), "ok");
+        assert_eq!(second.send("s"), "0");
+        assert_eq!(second.send("@other s"), "1");
+
+        // A transaction is private until it is committed
+        assert_eq!(first.send("begin"), "ok");
+        assert_eq!(first.send("i 2 b"), "ok");
+        assert_eq!(second.send("@other s"), "1");
+        assert_eq!(first.send("commit"), "ok");
+        assert_eq!(second.send("@other s"), "2");
+
+        // A transaction which started before a change of another session cannot be committed
+        assert_eq!(first.send("begin"), "ok");
+        assert_eq!(first.send("i 3 c"), "ok");
+        assert_eq!(second.send("i 9 z"), "ok");
+        assert!(first.send("commit").starts_with("error: line 9: `commit`: the trees were changed by another session"));
+        assert_eq!(first.send("s"), "2");
+
+        // The open transactions of a closed connection are rolled back
+        assert_eq!(second.send("begin"), "ok");
+        assert_eq!(second.send("x"), "ok");
+        drop(second);
+        first.send("shutdown");
+        assert_eq!(server.join().unwrap().tree("default").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_shutdown_closes_connections() {
+        let (address, server) = start(Options::default());
+        let mut idle = Client::connect(address);
+        assert_eq!(idle.send("i 1 a"), "ok");
+        Client::connect(address).send("shutdown");
+        server.join().unwrap();
+        assert!(idle.is_closed());
+        assert!(TcpStream::connect(address).is_err());
+    }
+
+    #[test]
+    fn test_long_lines() {
+        let (address, server) = start(Options::default());
+        let mut client = Client::connect(address);
+        // The longest line which is accepted, with its line break
+        assert_eq!(client.send(&format!("i 1 {}", "a".repeat(64 * 1024 - 5))), "ok");
+        assert_eq!(client.send(&format!("i 2 {}", "b".repeat(64 * 1024 - 4))), "error: a line is longer than 65536 bytes");
+        assert_eq!(client.send(&"c".repeat(1024 * 1024)), "error: a line is longer than 65536 bytes");
+        // The rest of the long lines was skipped
+        assert_eq!(client.send("s"), "1");
+        client.send("shutdown");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_local_clients() {
+        for local in ["127.0.0.1", "127.1.2.3", "::1", "::ffff:127.0.0.1"] {
+            assert!(is_local(local.parse().unwrap()), "{}", local);
+        }
+        for remote in ["192.168.1.2", "0.0.0.0", "2001:db8::1", "::ffff:10.0.0.1"] {
+            assert!(!is_local(remote.parse().unwrap()), "{}", remote);
+        }
+    }
+
+    #[test]
+    fn test_only_local_clients_may_use_files() {
+        let path = std::env::temp_dir().join(format!("rustsint-server-files-{}", std::process::id()));
+        let save = format!("save {}", path.display());
+        let load = format!("load {}", path.display());
+        let mut database = Database::new(Options::default()).unwrap();
+        let mut remote = client_session(&database, is_local("192.168.1.2".parse().unwrap()));
+        for command in [&save, &load] {
+            let mut output = Vec::new();
+            database.execute(&mut remote, command, &mut output).unwrap();
+            assert!(String::from_utf8(output).unwrap().ends_with("save and load are disabled\n"), "{}", command);
+        }
+        assert!(!path.exists());
+
+        let mut local = client_session(&database, is_local("127.0.0.1".parse().unwrap()));
+        for command in [&save, &load] {
+            let mut output = Vec::new();
+            database.execute(&mut local, command, &mut output).unwrap();
+            assert!(output.is_empty(), "{}", command);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_clients_cannot_undo() {
+        let (address, server) = start(Options::default());
+        let mut first = Client::connect(address);
+        let mut second = Client::connect(address);
+        assert_eq!(first.send("i 1 a"), "ok");
+        assert_eq!(second.send("u"), "error: line 1: `u`: undo and redo are disabled");
+        assert_eq!(first.send("u"), "error: line 2: `u`: undo and redo are disabled");
+        assert_eq!(first.send("history"), "error: line 3: `history`: undo and redo are disabled");
+        first.send("shutdown");
+        let mut database = server.join().unwrap();
+        assert_eq!(database.tree("default").unwrap().iter().collect::<Vec<_>>(), vec![(1, "a")]);
+        // The changes of the clients were not recorded, so undo has nothing to do after the server stopped
+        let mut session = database.session();
+        session.report_errors_to_output();
+        let mut output = Vec::new();
+        database.execute(&mut session, "u", &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "error: line 1: `u`: nothing to undo\n");
+    }
+
+    #[test]
+    fn test_strict_ends_the_connection() {
+        let (address, server) = start(Options { strict: true, ..Options::default() });
+        let mut client = Client::connect(address);
+        assert_eq!(client.send("i 1 a"), "ok");
+        assert_eq!(client.send("begin"), "ok");
+        assert_eq!(client.send("z"), "error: line 3: `z`: column 1: invalid command z.");
+        assert!(client.is_closed());
+        let mut client = Client::connect(address);
+        assert_eq!(client.send("s"), "1");
+        client.send("shutdown");
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::workload;
+    use crate::Tree;
+
+    fn tree(entries: &[(i32, &str)]) -> Tree {
+        let mut tree = Tree::new();
+        for (age, name) in entries {
+            tree.insert(*age, name.to_string());
+        }
+        tree
+    }
+
+    fn entries(tree: &Tree) -> Vec<(i32, &str)> {
+        tree.iter().collect()
+    }
+
+    #[test]
+    fn test_small() {
+        let a = tree(&[(1, "a"), (2, "b"), (3, "c")]);
+        let b = tree(&[(2, "b"), (3, "d"), (4, "e")]);
+        assert_eq!(entries(&a.union(&b)), vec![(1, "a"), (2, "b"), (3, "c"), (3, "d"), (4, "e")]);
+        assert_eq!(entries(&a.intersection(&b)), vec![(2, "b")]);
+        assert_eq!(entries(&a.difference(&b)), vec![(1, "a"), (3, "c")]);
+        assert_eq!(entries(&b.difference(&a)), vec![(3, "d"), (4, "e")]);
+        assert!(!a.same_entries(&b));
+        assert!(a.same_entries(&tree(&[(3, "c"), (2, "b"), (1, "a")])));
+        assert!(a.union(&Tree::new()).same_entries(&a));
+        assert!(a.intersection(&Tree::new()).is_empty());
+    }
+
+    #[test]
+    fn test_against_btree_set() {
+        let keys = workload::random(40, 7);
+        for seed in 0..10 {
+            let (mut a, mut b) = (Tree::new(), Tree::new());
+            let (mut set_a, mut set_b) = (BTreeSet::new(), BTreeSet::new());
+            for (i, op) in workload::mixed(&keys, 200, seed).into_iter().enumerate() {
+                let (tree, set) = if i % 2 == 0 { (&mut a, &mut set_a) } else { (&mut b, &mut set_b) };
+                if let workload::Op::Insert(age, name) = op {
+                    set.insert((age, name.clone()));
+                    tree.insert(age, name);
+                }
+            }
+            let check = |tree: Tree, expected: Vec<&(i32, String)>| {
+                assert_eq!(tree.validate(), Ok(()));
+                let expected: Vec<(i32, &str)> = expected.iter().map(|(age, name)| (*age, name.as_str())).collect();
+                assert_eq!(entries(&tree), expected);
+            };
+            check(a.union(&b), set_a.union(&set_b).collect());
+            check(a.intersection(&b), set_a.intersection(&set_b).collect());
+            check(a.difference(&b), set_a.difference(&set_b).collect());
+            assert_eq!(a.same_entries(&b), set_a == set_b);
+        }
+    }
+}
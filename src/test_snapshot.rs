@@ -0,0 +1,158 @@
+#[cfg(test)]
+mod tests {
+    use crate::tree::{Crc32, SnapshotError, SNAPSHOT_VERSION};
+    use crate::{command_loop, Tree};
+
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::new();
+        tree.insert(42, "Peter".to_string());
+        tree.insert(21, "Joanna".to_string());
+        tree.insert(83, "Margaretha".to_string());
+        tree.insert(5, "Frank".to_string());
+        tree.insert(1, "Peter".to_string());
+        tree.insert(2, "Zoë".to_string());
+        tree
+    }
+
+    fn snapshot(tree: &Tree) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tree.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// Replace the checksum at the end of the snapshot by the checksum of the data before it
+    fn fix_checksum(bytes: &mut Vec<u8>) {
+        let len = bytes.len() - 4;
+        bytes.truncate(len);
+        let mut crc = Crc32::new();
+        crc.update(bytes);
+        bytes.extend_from_slice(&crc.finish().to_le_bytes());
+    }
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tree = sample_tree();
+        let loaded = Tree::load_from(&snapshot(&tree)[..]).unwrap();
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), tree.iter().collect::<Vec<_>>());
+
+        let loaded = Tree::load_from(&snapshot(&Tree::new())[..]).unwrap();
+        assert!(loaded.iter().next().is_none());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut tree = Tree::new();
+        tree.insert(7, "ab".to_string());
+        let bytes = snapshot(&tree);
+        assert_eq!(&bytes[0..4], b"AVLT");
+        assert_eq!(&bytes[4..6], &SNAPSHOT_VERSION.to_le_bytes());
+        assert_eq!(&bytes[6..14], &1u64.to_le_bytes());
+        assert_eq!(&bytes[14..18], &7i32.to_le_bytes());
+        assert_eq!(&bytes[18..22], &2u32.to_le_bytes());
+        assert_eq!(&bytes[22..24], b"ab");
+        assert_eq!(bytes.len(), 28);
+    }
+
+    #[test]
+    fn test_load_is_balanced() {
+        let mut tree = Tree::new();
+        for age in 0..1000 {
+            tree.insert(age, "Name".to_string());
+        }
+        let loaded = Tree::load_from(&snapshot(&tree)[..]).unwrap();
+        let json = loaded.to_string();
+        // A perfectly balanced tree of 1000 entries has height 10
+        assert!(loaded.to_dot().contains("h=10 "));
+        assert!(!loaded.to_dot().contains("h=11 "));
+        assert_eq!(json.matches("\"Name\"").count(), 1000);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = snapshot(&sample_tree());
+        for len in 0..bytes.len() {
+            match Tree::load_from(&bytes[..len]) {
+                Err(SnapshotError::Truncated) => {}
+                other => panic!("loading {} of {} bytes returned {:?}", len, bytes.len(), other.map(|_| ()))
+            }
+        }
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let bytes = snapshot(&sample_tree());
+        // Flip a bit in every byte of the records and the checksum
+        for index in 14..bytes.len() {
+            let mut corrupted = bytes.clone();
+            corrupted[index] ^= 0x10;
+            assert!(Tree::load_from(&corrupted[..]).is_err(), "corruption at byte {} was not detected", index);
+        }
+    }
+
+    #[test]
+    fn test_bad_header() {
+        let mut bytes = snapshot(&sample_tree());
+        bytes[0] = b'X';
+        match Tree::load_from(&bytes[..]) {
+            Err(SnapshotError::BadMagic(magic)) => assert_eq!(&magic, b"XVLT"),
+            other => panic!("unexpected result {:?}", other.map(|_| ()))
+        }
+
+        let mut bytes = snapshot(&sample_tree());
+        bytes[4] = 99;
+        match Tree::load_from(&bytes[..]) {
+            Err(SnapshotError::UnsupportedVersion(99)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ()))
+        }
+    }
+
+    #[test]
+    fn test_not_sorted() {
+        let mut tree = Tree::new();
+        tree.insert(1, "a".to_string());
+        tree.insert(2, "b".to_string());
+        let mut bytes = snapshot(&tree);
+        // Swap the ages of both records, the names have length 1
+        bytes[14..18].copy_from_slice(&2i32.to_le_bytes());
+        bytes[23..27].copy_from_slice(&1i32.to_le_bytes());
+        fix_checksum(&mut bytes);
+        match Tree::load_from(&bytes[..]) {
+            Err(SnapshotError::NotSorted(1)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ()))
+        }
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let mut tree = Tree::new();
+        tree.insert(1, "a".to_string());
+        let mut bytes = snapshot(&tree);
+        bytes[22] = 0xFF;
+        fix_checksum(&mut bytes);
+        match Tree::load_from(&bytes[..]) {
+            Err(SnapshotError::InvalidName(0)) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ()))
+        }
+    }
+
+    #[test]
+    fn test_commands() {
+        let path = std::env::temp_dir().join(format!("rustsint-test-{}.snapshot", std::process::id()));
+        let path = path.to_str().unwrap();
+        let input = format!("i 2 b\ni 1 a\ni 3 c\nsave {0}\nx\np\nload {0}\np\nload {0}.missing\np\n", path);
+
+        let mut output = Vec::new();
+        command_loop(&mut input.as_bytes(), &mut output).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let tree = "[{\"2\":\"b\"},[{\"1\":\"a\"},null,null],[{\"3\":\"c\"},null,null]]";
+        assert_eq!(String::from_utf8(output).unwrap(), format!("null\n{0}\n{0}\n", tree));
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::workload;
+    use crate::{command_loop, Tree};
+
+    fn insert_all(tree: &mut Tree, ages: &[i32]) {
+        for &age in ages {
+            tree.insert(age, "a".to_string());
+        }
+    }
+
+    #[test]
+    fn test_rotations() {
+        let mut tree = Tree::new();
+        // 3 is a single rotation (RR), 4 a double rotation (RL)
+        insert_all(&mut tree, &[1, 2, 3, 5, 4]);
+        let stats = tree.stats();
+        assert_eq!(stats.inserts, 5);
+        assert_eq!(stats.single_rotations, 1);
+        assert_eq!(stats.double_rotations, 1);
+        assert_eq!(stats.insert_rebalances, 2);
+        assert_eq!(stats.max_insert_rebalances, 1);
+        assert_eq!(stats.max_height, 3);
+
+        // The root 2 becomes right heavy and is rotated
+        tree.erase(1, "a".to_string());
+        tree.erase(7, "a".to_string());
+        let stats = tree.stats();
+        assert_eq!(stats.erases, 2);
+        assert_eq!(stats.erase_rebalances, 1);
+        assert_eq!(stats.single_rotations, 2);
+        assert_eq!(stats.max_height, 3);
+
+        tree.reset_stats();
+        assert_eq!(tree.stats().inserts, 0);
+        assert_eq!(tree.stats().max_height, 3);
+    }
+
+    #[test]
+    fn test_find_visits() {
+        let mut tree = Tree::new();
+        insert_all(&mut tree, &[2, 1, 4, 3, 5]);
+        assert!(tree.find(2, "a".to_string()));
+        assert!(tree.find(3, "a".to_string()));
+        assert!(!tree.find(0, "a".to_string()));
+        let stats = tree.stats();
+        assert_eq!(stats.finds, 3);
+        assert_eq!(stats.find_visits, 1 + 3 + 2);
+        assert_eq!(stats.max_find_visits, 3);
+        assert_eq!(stats.visits_per_find(), 2.0);
+    }
+
+    #[test]
+    fn test_logarithmic_bounds() {
+        let n = 10000;
+        // Bound on the height of an AVL tree with n nodes
+        let bound = (1.45 * ((n + 2) as f64).log2()) as u64;
+        for keys in [workload::sequential(n), workload::random(n, 1)] {
+            let mut tree = Tree::new();
+            for (age, name) in &keys {
+                tree.insert(*age, name.clone());
+            }
+            for (age, name) in keys.iter().chain(&workload::misses(n, 1)) {
+                tree.find(*age, name.clone());
+            }
+            for (age, name) in &keys {
+                tree.erase(*age, name.clone());
+            }
+            let stats = tree.stats();
+            assert!(stats.max_height as u64 <= bound);
+            assert!(stats.max_find_visits <= bound);
+            // An insert rotates at most once
+            assert_eq!(stats.max_insert_rebalances, 1);
+            assert!(stats.max_erase_rebalances <= bound);
+        }
+    }
+
+    #[test]
+    fn test_command() {
+        let mut output = Vec::new();
+        command_loop(&mut "i 1 a\ni 2 a\ni 3 a\nc 3 a\nstats\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "y\n\
+                                                        inserts: 3\n\
+                                                        erases: 0\n\
+                                                        finds: 1\n\
+                                                        single rotations: 1\n\
+                                                        double rotations: 0\n\
+                                                        rebalances per insert: 0.33 avg, 1 max\n\
+                                                        rebalances per erase: 0.00 avg, 0 max\n\
+                                                        nodes visited per find: 2.00 avg, 2 max\n\
+                                                        max height: 2\n");
+    }
+}
@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use crate::{command_loop, Interpreter, Options};
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        command_loop(&mut input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    const BUILD: &str = "i 5 a\ni 3 b\ni 8 c\ni 1 d\ni 4 e\ne 8 c\n";
+
+    #[test]
+    fn test_rollback_restores_shape() {
+        let before = run(&format!("{}a v\n", BUILD));
+        assert_eq!(run(&format!("{}begin\ni 9 z\ne 1 d\nx\ni 2 y\nrollback\na v\n", BUILD)), before);
+    }
+
+    #[test]
+    fn test_commit() {
+        assert_eq!(run("i 1 a\nbegin\ni 2 b\ne 1 a\ncommit\np\nhistory\n"),
+                   "[{\"2\":\"b\"},null,null]\n@default i 1 a\n@default i 2 b\n@default e 1 a\n");
+    }
+
+    #[test]
+    fn test_savepoints() {
+        let input = "i 1 a\nbegin\ni 2 b\nbegin\ni 3 c\nrollback\nbegin\ni 4 d\ncommit\ns\nrollback\ns\n";
+        assert_eq!(run(input), "3\n1\n");
+        assert_eq!(run("begin\ni 1 a\nbegin\ni 2 b\ncommit\ncommit\ns\n"), "2\n");
+    }
+
+    #[test]
+    fn test_undo_inside_transaction() {
+        assert_eq!(run("i 1 a\nbegin\ni 2 b\nu\nu\nhistory\ncommit\ns\nu\ns\n"), "1\n0\n");
+    }
+
+    #[test]
+    fn test_named_trees() {
+        let input = "new t\nbegin\nnew u\n@t i 1 a\nuse u\ndrop t\nrollback\nlist\n";
+        assert_eq!(run(input), "* default 0\n  t 0\n");
+    }
+
+    #[test]
+    fn test_strict_error_rolls_back() {
+        let options = Options { strict: true, ..Options::default() };
+        let mut interpreter = Interpreter::new(options).unwrap();
+        interpreter.run(&mut "i 1 a\n".as_bytes(), &mut Vec::new()).unwrap();
+        assert!(interpreter.run(&mut "begin\ni 2 b\nbegin\ni 3 c\ni x d\ncommit\ncommit\n".as_bytes(), &mut Vec::new()).is_err());
+        assert!(!interpreter.in_transaction());
+        assert_eq!(interpreter.tree().iter().collect::<Vec<_>>(), vec![(1, "a")]);
+    }
+
+    #[test]
+    fn test_save_inside_transaction() {
+        let path = std::env::temp_dir().join(format!("rustsint-transaction-save-{}", std::process::id()));
+        let options = Options { strict: true, ..Options::default() };
+        let mut interpreter = Interpreter::new(options).unwrap();
+        let input = format!("begin\ni 1 a\nsave {}\n", path.display());
+        let error = interpreter.run(&mut input.as_bytes(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), format!("line 3: `save {}`: save is not allowed inside a transaction", path.display()));
+        assert!(!interpreter.in_transaction());
+        assert_eq!(interpreter.tree().len(), 0);
+        assert!(!path.exists());
+
+        assert_eq!(run(&format!("begin\ni 1 a\nsave {}\nrollback\ns\n", path.display())), "0\n");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_errors() {
+        let options = Options { strict: true, ..Options::default() };
+        let mut interpreter = Interpreter::new(options).unwrap();
+        let error = interpreter.run(&mut "commit\n".as_bytes(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: `commit`: no transaction to commit");
+        let error = interpreter.run(&mut "rollback\n".as_bytes(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: `rollback`: no transaction to roll back");
+    }
+}
@@ -1,7 +1,26 @@
 #[cfg(test)]
 mod tests {
-    use crate::Tree;
-    use rand::Rng;
+    use std::collections::BTreeSet;
+
+    use crate::tree::InvariantError;
+    use crate::workload::{self, Op};
+    use crate::{AsciiOptions, Tree};
+
+    /// Insert into the tree and check its invariants afterwards
+    fn insert(tree: &mut Tree, age: i32, name: &str) {
+        tree.insert(age, name.to_string());
+        if let Err(error) = tree.validate() {
+            panic!("invalid tree after inserting {{{}: {:?}}}: {}", age, name, error);
+        }
+    }
+
+    /// Erase from the tree and check its invariants afterwards
+    fn erase(tree: &mut Tree, age: i32, name: &str) {
+        tree.erase(age, name.to_string());
+        if let Err(error) = tree.validate() {
+            panic!("invalid tree after erasing {{{}: {:?}}}: {}", age, name, error);
+        }
+    }
 
     /* The constructed AVL Tree would be
          *                 5(H:2)(F: 0)
@@ -14,12 +33,12 @@ mod tests {
     #[test]
     fn test_basic() {
         let mut tree = Tree::new();
-        tree.insert(42, "Peter".to_string());
-        tree.insert(21, "Joanna".to_string());
-        tree.insert(83, "Margaretha".to_string());
-        tree.insert(5, "Frank".to_string());
-        tree.insert(1, "Peter".to_string());
-        tree.insert(2, "Mark".to_string());
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
 
         tree.print();
     }
@@ -27,12 +46,12 @@ mod tests {
     #[test]
     fn test_find() {
         let mut tree = Tree::new();
-        tree.insert(42, "Peter".to_string());
-        tree.insert(21, "Joanna".to_string());
-        tree.insert(83, "Margaretha".to_string());
-        tree.insert(5, "Frank".to_string());
-        tree.insert(1, "Peter".to_string());
-        tree.insert(2, "Mark".to_string());
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
 
         if !tree.find(42, "Peter".to_string()) {
             panic!("Could not find an item that was recently inserted");
@@ -42,14 +61,14 @@ mod tests {
     #[test]
     fn test_erase() {
         let mut tree = Tree::new();
-        tree.insert(42, "Peter".to_string());
-        tree.insert(21, "Joanna".to_string());
-        tree.insert(83, "Margaretha".to_string());
-        tree.insert(5, "Frank".to_string());
-        tree.insert(1, "Peter".to_string());
-        tree.insert(2, "Mark".to_string());
-
-        tree.erase(2, "Mark".to_string());
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
+
+        erase(&mut tree, 2, "Mark");
         if tree.find(2, "Mark".to_string()) {
             panic!("Found an item that was supposed to be removed (leaf node)");
         }
@@ -58,14 +77,14 @@ mod tests {
     #[test]
     fn test_insert() {
         let mut tree = Tree::new();
-        tree.insert(42, "Peter".to_string());
-        tree.insert(21, "Joanna".to_string());
-        tree.insert(83, "Margaretha".to_string());
-        tree.insert(5, "Frank".to_string());
-        tree.insert(1, "Peter".to_string());
-        tree.insert(2, "Mark".to_string());
-
-        tree.erase(2, "Mark".to_string());
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
+
+        erase(&mut tree, 2, "Mark");
         if tree.find(2, "Mark".to_string()) {
             panic!("Found an item that was supposed to be removed (leaf node)");
         }
@@ -74,14 +93,15 @@ mod tests {
     #[test]
     fn test_delete() {
         let mut tree = Tree::new();
-        tree.insert(42, "Peter".to_string());
-        tree.insert(21, "Joanna".to_string());
-        tree.insert(83, "Margaretha".to_string());
-        tree.insert(5, "Frank".to_string());
-        tree.insert(1, "Peter".to_string());
-        tree.insert(2, "Mark".to_string());
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
 
         tree.delete();
+        tree.validate().unwrap();
 
         if tree.find(2, "Mark".to_string()) {
             panic!("Found an item that was supposed to be removed (leaf node)");
@@ -89,24 +109,187 @@ mod tests {
     }
 
     #[test]
-    fn test_stress() {
+    fn test_same_age() {
+        // Entries are ordered by age and then by name, the baseline told them apart by the age only
         let mut tree = Tree::new();
-        let mut rng = rand::thread_rng();
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 42, "Anna");
+        insert(&mut tree, 42, "Zoe");
+        insert(&mut tree, 42, "Anna");
+        assert_eq!(tree.to_string(), "[{\"42\":\"Peter\"},[{\"42\":\"Anna\"},null,null],[{\"42\":\"Zoe\"},null,null]]");
+
+        if tree.find(42, "Mark".to_string()) {
+            panic!("Found an item with the same age but a different name");
+        }
+
+        erase(&mut tree, 42, "Mark");
+        erase(&mut tree, 42, "Zoe");
+        if tree.find(42, "Zoe".to_string()) || !tree.find(42, "Anna".to_string()) || !tree.find(42, "Peter".to_string()) {
+            panic!("Erase removed the wrong item with the same age");
+        }
+
+        let entries: Vec<(i32, &str)> = tree.iter().collect();
+        assert_eq!(entries, vec![(42, "Anna"), (42, "Peter")]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(Tree::new().validate(), Ok(()));
+        assert_eq!(Tree::from_sorted(vec![(1, "a".to_string()), (1, "b".to_string()), (2, "a".to_string())]).validate(), Ok(()));
+
+        let tree = Tree::from_sorted(vec![(1, "a".to_string()), (3, "c".to_string()), (2, "b".to_string())]);
+        assert_eq!(tree.validate(), Err(InvariantError::NotSorted {
+            age: 2, name: "b".to_string(), previous_age: 3, previous_name: "c".to_string() }));
+
+        let tree = Tree::from_sorted(vec![(1, "b".to_string()), (1, "a".to_string())]);
+        let error = tree.validate().unwrap_err();
+        assert_eq!(error.to_string(), r#"node {1: "a"} is not greater than the node {1: "b"} before it"#);
+    }
+
+    #[test]
+    fn test_display() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.to_string(), "null");
+
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        assert_eq!(tree.to_string(), r#"[{"42":"Peter"},[{"21":"Joanna"},null,null],null]"#);
+
+        let mut buffer = Vec::new();
+        tree.write_json(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), tree.to_string());
+    }
+
+    #[test]
+    fn test_display_escapes_names() {
+        let mut tree = Tree::new();
+        tree.insert(1, "a\"b\\c".to_string());
+        assert_eq!(tree.to_string(), r#"[{"1":"a\"b\\c"},null,null]"#);
+
+        let mut tree = Tree::new();
+        insert(&mut tree, 1, "\n\t\u{1}Zoë");
+        assert_eq!(tree.to_string(), r#"[{"1":"\n\t\u0001Zoë"},null,null]"#);
+    }
 
-        for _ in 0..100000 {
-            let age: i32 = rng.gen::<i32>() % 1000;
-            let name = "Name".to_string();
+    #[test]
+    fn test_display_pretty() {
+        let mut tree = Tree::new();
+        assert_eq!(format!("{:#}", tree), "null");
+
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        let expected = "[\n  {\"42\": \"Peter\"},\n  [\n    {\"21\": \"Joanna\"},\n    null,\n    null\n  ],\n  null\n]";
+        assert_eq!(format!("{:#}", tree), expected);
+
+        let mut buffer = Vec::new();
+        tree.write_json_pretty(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut tree = Tree::new();
+        assert!(tree.to_dot().contains("null0 [shape=point];"));
+
+        insert(&mut tree, 42, "Peter");
+        tree.insert(21, "Jo\"anna".to_string());
+        let dot = tree.to_dot();
+        assert!(dot.starts_with("digraph tree {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains(r#"n0 [label="42: Peter\nh=2 bf=1"];"#));
+        assert!(dot.contains(r#"n1 [label="21: Jo\"anna\nh=1 bf=0"];"#));
+        assert!(dot.contains("n0 -> n1;"));
+        // The missing right child of the root is drawn as well
+        assert!(dot.contains("null4 [shape=point];"));
+        assert!(dot.contains("n0 -> null4;"));
+    }
+
+    #[test]
+    fn test_to_dot_escapes_names() {
+        let label = |name: &str| {
+            let mut tree = Tree::new();
+            tree.insert(1, name.to_string());
+            let dot = tree.to_dot();
+            let start = dot.find("label=\"").unwrap() + 7;
+            dot[start..start + dot[start..].find("\\nh=").unwrap()].to_string()
+        };
+        assert_eq!(label("a\u{1}b"), r"1: a\\u{1}b");
+        assert_eq!(label("a\\u{1}b"), r"1: a\\\\u{1}b");
+        assert_eq!(label("a\\b\"c"), r#"1: a\\\\b\"c"#);
+        assert_eq!(label("a\nb"), r"1: a\nb");
+        assert_ne!(label("a\u{7f}"), label("a"));
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let mut tree = Tree::new();
+        assert_eq!(tree.render_ascii(), "·\n");
+
+        insert(&mut tree, 42, "Peter");
+        insert(&mut tree, 21, "Joanna");
+        insert(&mut tree, 83, "Margaretha");
+        insert(&mut tree, 5, "Frank");
+        insert(&mut tree, 1, "Peter");
+        insert(&mut tree, 2, "Mark");
+        assert_eq!(tree.render_ascii(), concat!(
+            "{5: Frank}\n",
+            "├── {1: Peter}\n",
+            "│   ├── ·\n",
+            "│   └── {2: Mark}\n",
+            "└── {42: Peter}\n",
+            "    ├── {21: Joanna}\n",
+            "    └── {83: Margaretha}\n"));
+    }
+
+    #[test]
+    fn test_render_ascii_options() {
+        let mut tree = Tree::new();
+        insert(&mut tree, 42, "Bartholomew");
+        insert(&mut tree, 21, "Jo");
+
+        let options = AsciiOptions { show_details: true, max_name_len: 5 };
+        assert_eq!(tree.render_ascii_with(&options), concat!(
+            "{42: Bart…} h=2 bf=1\n",
+            "├── {21: Jo} h=1 bf=0\n",
+            "└── ·\n"));
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut tree = Tree::new();
+        assert_eq!(format!("{:?}", tree), "Tree { root: None }");
+
+        insert(&mut tree, 42, "Peter");
+        assert_eq!(format!("{:?}", tree),
+                   r#"Tree { root: Some(Node { age: 42, name: "Peter", height: 1, left: None, right: None }) }"#);
+    }
+
+    #[test]
+    fn test_stress() {
+        let mut tree = Tree::new();
+        let mut model = BTreeSet::new();
+        let keys = workload::random(1000, 1);
 
-            if tree.find(age, name.clone()) {
-                tree.erase(age,name);
-            }else{
-                tree.insert(age,name);
+        for op in workload::mixed(&keys, 100000, 2) {
+            match op {
+                Op::Insert(age, name) => {
+                    insert(&mut tree, age, &name);
+                    model.insert((age, name));
+                }
+                Op::Erase(age, name) => {
+                    erase(&mut tree, age, &name);
+                    model.remove(&(age, name));
+                }
+                Op::Find(age, name) => {
+                    assert_eq!(tree.find(age, name.clone()), model.contains(&(age, name)));
+                }
             }
         }
+        assert!(tree.iter().eq(model.iter().map(|(age, name)| (*age, name.as_str()))));
 
         tree.delete();
 
         println!("Stress test succeeded\n")
 
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,238 @@
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use crate::wal::{Mutation, Wal, WalOptions};
+    use crate::{command_loop_with, Options};
+
+    /// Returns a fresh directory for the files of one test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustsint-wal-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn options(dir: &Path, snapshot: bool, compact_after: usize) -> Options {
+        Options {
+            wal: Some(WalOptions {
+                log_path: dir.join("tree.wal"),
+                snapshot_path: if snapshot { Some(dir.join("tree.snapshot")) } else { None },
+                compact_after,
+            }),
+            ..Options::default()
+        }
+    }
+
+    fn run(input: &str, options: &Options) -> String {
+        let mut output = Vec::new();
+        command_loop_with(&mut input.as_bytes(), &mut output, options).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_recovery() {
+        let dir = test_dir("recovery");
+        let options = options(&dir, false, 0);
+
+        let printed = run("i 15 a\ni 10 b\ni 20 c\ni 8 d\ne 10 b\np\n", &options);
+        assert_eq!(run("p\n", &options), printed);
+
+        // Reset is logged as well
+        run("x\ni 1 z\n", &options);
+        assert_eq!(run("p\n", &options), "[{\"1\":\"z\"},null,null]\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crash_mid_record() {
+        let dir = test_dir("crash");
+        let options = options(&dir, false, 0);
+        let log_path = dir.join("tree.wal");
+
+        run("i 15 a\ni 10 b\n", &options);
+        let complete_len = fs::metadata(&log_path).unwrap().len();
+        run("i 20 c\n", &options);
+        let full = fs::read(&log_path).unwrap();
+
+        // Every cut inside the last record loses exactly that record
+        for len in complete_len..full.len() as u64 {
+            fs::write(&log_path, &full[..len as usize]).unwrap();
+            assert_eq!(Wal::open(options.wal.as_ref().unwrap()).unwrap().0.discarded(), len - complete_len);
+            fs::write(&log_path, &full[..len as usize]).unwrap();
+            assert_eq!(run("p\n", &options), "[{\"15\":\"a\"},[{\"10\":\"b\"},null,null],null]\n");
+            assert_eq!(fs::metadata(&log_path).unwrap().len(), complete_len);
+        }
+
+        // New records are appended after the last complete one
+        run("i 30 d\n", &options);
+        assert_eq!(run("c 30 d\nc 20 c\n", &options), "y\nn\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_record() {
+        let dir = test_dir("corrupted");
+        let options = options(&dir, false, 0);
+        let log_path = dir.join("tree.wal");
+
+        run("i 15 a\ni 10 b\n", &options);
+        let mut bytes = fs::read(&log_path).unwrap();
+        let last = bytes.len() - 6;
+        bytes[last] ^= 0x01;
+        fs::write(&log_path, &bytes).unwrap();
+
+        assert_eq!(run("c 15 a\nc 10 b\n", &options), "y\nn\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_record_before_valid_ones() {
+        let dir = test_dir("corrupted-middle");
+        let options = options(&dir, false, 0);
+        let log_path = dir.join("tree.wal");
+
+        run("i 15 a\ni 10 b\n", &options);
+        let mut bytes = fs::read(&log_path).unwrap();
+        // The age of the first record, after the header, the length and the operation byte
+        bytes[6 + 4 + 1] ^= 0x01;
+        fs::write(&log_path, &bytes).unwrap();
+
+        let error = Wal::open(options.wal.as_ref().unwrap()).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("damaged record at offset 6"), "{}", error);
+        // The log is left as it is
+        assert_eq!(fs::read(&log_path).unwrap(), bytes);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_length_before_valid_ones() {
+        let dir = test_dir("corrupted-length");
+        let options = options(&dir, false, 0);
+        let log_path = dir.join("tree.wal");
+
+        run("i 15 a\ni 10 b\n", &options);
+        let mut bytes = fs::read(&log_path).unwrap();
+        // The length of the first record points past the end of the log
+        bytes[6 + 3] = 0x7f;
+        fs::write(&log_path, &bytes).unwrap();
+
+        let error = Wal::open(options.wal.as_ref().unwrap()).err().unwrap();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("damaged record at offset 6"), "{}", error);
+        assert_eq!(fs::read(&log_path).unwrap(), bytes);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_compaction() {
+        let dir = test_dir("compaction");
+        let options = options(&dir, true, 3);
+        let log_path = dir.join("tree.wal");
+
+        let printed = run("i 1 a\ni 2 b\ni 3 c\ni 4 d\ne 1 a\np\n", &options);
+        assert!(dir.join("tree.snapshot").exists());
+        // Three records went into the snapshot, two are left in the log
+        let (_, tree) = Wal::open(options.wal.as_ref().unwrap()).unwrap();
+        assert_eq!(tree.iter().collect::<Vec<_>>(), vec![(2, "b"), (3, "c"), (4, "d")]);
+        assert!(fs::metadata(&log_path).unwrap().len() < 64);
+        assert_eq!(run("p\n", &options), printed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_after_snapshot_is_idempotent() {
+        let dir = test_dir("idempotent");
+        let options = options(&dir, true, 0);
+        let wal_options = options.wal.clone().unwrap();
+
+        let (mut wal, mut tree) = Wal::open(&wal_options).unwrap();
+        let mutations = vec![
+            Mutation::Insert{age: 1, name: "a".to_string()},
+            Mutation::Insert{age: 2, name: "b".to_string()},
+            Mutation::Erase{age: 1, name: "a".to_string()},
+        ];
+        wal.append(&mutations).unwrap();
+        for mutation in &mutations {
+            mutation.apply(&mut tree);
+        }
+        // Simulate a crash after the snapshot was written, but before the log was emptied
+        tree.save_to(fs::File::create(dir.join("tree.snapshot")).unwrap()).unwrap();
+        drop(wal);
+
+        let (_, recovered) = Wal::open(&wal_options).unwrap();
+        assert_eq!(recovered.iter().collect::<Vec<_>>(), vec![(2, "b")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_is_logged() {
+        let dir = test_dir("load");
+        let options = options(&dir, false, 0);
+        let snapshot = dir.join("other.snapshot");
+
+        run(&format!("i 1 a\ni 2 b\nsave {}\nx\n", snapshot.display()), &Options::default());
+        run(&format!("i 9 z\nload {}\n", snapshot.display()), &options);
+        fs::remove_file(&snapshot).unwrap();
+
+        assert_eq!(run("c 1 a\nc 2 b\nc 9 z\n", &options), "y\ny\nn\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_only_default_tree_is_logged() {
+        let dir = test_dir("named");
+        let options = options(&dir, false, 0);
+
+        run("new a\n@a i 1 a\n@a i 2 b\ni 3 c\nunion a\n@a x\n", &options);
+        assert_eq!(run("list\np\n", &options), "* default 3\n[{\"2\":\"b\"},[{\"1\":\"a\"},null,null],[{\"3\":\"c\"},null,null]]\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_undo_is_logged() {
+        let dir = test_dir("undo");
+        let options = options(&dir, false, 0);
+
+        let printed = run("i 1 a\ni 2 b\ni 3 c\nx\nu\ne 2 b\nu\nu\np\n", &options);
+        assert_eq!(run("p\n", &options), printed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transactions_are_logged_on_commit() {
+        let dir = test_dir("transaction");
+        let options = options(&dir, false, 0);
+
+        run("i 1 a\nbegin\ni 2 b\nbegin\ni 3 c\ncommit\ncommit\nbegin\ni 4 d\n", &options);
+        assert_eq!(Wal::open(&options.wal.clone().unwrap()).unwrap().1.iter().collect::<Vec<_>>(), vec![(1, "a"), (2, "b"), (3, "c")]);
+        assert_eq!(run("s\n", &options), "3\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_commit_logs_only_the_transaction() {
+        let plain = test_dir("transaction-plain");
+        let transaction = test_dir("transaction-own");
+
+        run("i 1 a\ni 2 b\ni 5 e\ne 2 b\n", &options(&plain, false, 0));
+        // A commit appends what the transaction did, without the savepoint which was rolled back
+        run("i 1 a\nbegin\ni 2 b\nbegin\ni 3 c\nrollback\nbegin\ni 5 e\ncommit\ne 2 b\ncommit\n", &options(&transaction, false, 0));
+        assert_eq!(fs::read(transaction.join("tree.wal")).unwrap(), fs::read(plain.join("tree.wal")).unwrap());
+        fs::remove_dir_all(&plain).unwrap();
+        fs::remove_dir_all(&transaction).unwrap();
+    }
+
+    #[test]
+    fn test_not_a_log() {
+        let dir = test_dir("not-a-log");
+        let options = options(&dir, false, 0);
+        fs::write(dir.join("tree.wal"), b"hello world").unwrap();
+
+        let mut output = Vec::new();
+        assert!(command_loop_with(&mut "p\n".as_bytes(), &mut output, &options).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
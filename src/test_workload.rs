@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::workload::{self, Op};
+    use crate::Tree;
+
+    #[test]
+    fn test_seeded() {
+        assert_eq!(workload::random(100, 7), workload::random(100, 7));
+        assert_ne!(workload::random(100, 7), workload::random(100, 8));
+        let keys = workload::random(10, 7);
+        assert_eq!(workload::mixed(&keys, 100, 7), workload::mixed(&keys, 100, 7));
+    }
+
+    #[test]
+    fn test_keys() {
+        let sequential = workload::sequential(1000);
+        assert!(crate::tree::is_strictly_sorted(&sequential));
+
+        let random = workload::random(1000, 1);
+        assert_eq!(random.iter().collect::<BTreeSet<_>>().len(), 1000);
+
+        let mut tree = Tree::from_sorted(sequential);
+        for (age, name) in random {
+            tree.insert(age, name);
+        }
+        for (age, name) in workload::misses(1000, 1) {
+            assert!(!tree.find(age, name));
+        }
+    }
+
+    #[test]
+    fn test_mixed() {
+        let keys = workload::random(10, 3);
+        let ops = workload::mixed(&keys, 1000, 3);
+        let finds = ops.iter().filter(|op| matches!(op, Op::Find(..))).count();
+        assert!(finds > 400 && finds < 600);
+
+        let mut tree = Tree::new();
+        for op in &ops {
+            op.apply(&mut tree);
+        }
+        assert!(tree.iter().all(|(age, name)| keys.contains(&(age, name.to_string()))));
+    }
+}
@@ -0,0 +1,150 @@
+//! Step-by-step trace of inserts and erases, shown by the command interpreter in trace mode.
+//!
+//! The trace of a command starts with the operation and its data, followed by one indented line
+//! per step. The nodes compared on the way down are shown as one `path:` line; every rotation
+//! (with the subtree after it), every repaired node (with its `LL`, `RR`, `LR` or `RL` case) and
+//! the inserted or removed node get a line of their own. A double rotation shows both of its
+//! rotations, the first one with the rotated child subtree. The trace ends with the whole tree:
+//!
+//! ```text
+//! insert {3: c}
+//!   path: {1: a} -> {2: b}
+//!   inserted {3: c}
+//!   rotate left at {1: a}:
+//!     {2: b}
+//!     ├── {1: a}
+//!     └── {3: c}
+//!   RR at {1: a}
+//!   tree:
+//!     {2: b}
+//!     ├── {1: a}
+//!     └── {3: c}
+//! ```
+
+use crate::tree::{key_label, Node, Rotation};
+use crate::{AsciiOptions, Tree, TreeObserver};
+
+/// Observer which writes the trace of a single insert or erase
+pub struct Tracer {
+    /// The trace written so far
+    text: String,
+    /// Nodes visited since the last step which was not a visit
+    path: Vec<String>,
+    /// Whether a node was inserted or removed
+    changed: bool,
+    /// Options of the labels and trees in the trace
+    options: AsciiOptions,
+}
+
+impl Tracer {
+    /// Start the trace of an insert.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - Data which is inserted
+    pub fn insert(key: (i32, &str)) -> Tracer {
+        Tracer::new("insert", key)
+    }
+
+    /// Start the trace of an erase.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - Data which is erased
+    pub fn erase(key: (i32, &str)) -> Tracer {
+        Tracer::new("erase", key)
+    }
+
+    fn new(operation: &str, key: (i32, &str)) -> Tracer {
+        let options = AsciiOptions::default();
+        let text = format!("{} {}\n", operation, key_label(key, &options));
+        Tracer { text, path: Vec::new(), changed: false, options }
+    }
+
+    /// Returns the complete trace, ending with the given tree.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree after the operation
+    pub fn finish(mut self, tree: &Tree) -> String {
+        self.flush_path();
+        if !self.changed {
+            self.text.push_str("  unchanged\n");
+        }
+        self.text.push_str("  tree:\n");
+        push_indented(&mut self.text, &tree.render_ascii_with(&self.options));
+        self.text
+    }
+
+    /// Write the nodes visited so far as a `path:` line.
+    fn flush_path(&mut self) {
+        if !self.path.is_empty() {
+            self.text.push_str(&format!("  path: {}\n", self.path.join(" -> ")));
+            self.path.clear();
+        }
+    }
+
+    /// Write a step of the trace.
+    fn step(&mut self, line: String) {
+        self.flush_path();
+        self.text.push_str("  ");
+        self.text.push_str(&line);
+        self.text.push('\n');
+    }
+}
+
+impl TreeObserver for Tracer {
+    fn node_visited(&mut self, key: (i32, &str)) {
+        self.path.push(key_label(key, &self.options));
+    }
+
+    fn node_inserted(&mut self, key: (i32, &str)) {
+        self.changed = true;
+        let line = format!("inserted {}", key_label(key, &self.options));
+        self.step(line);
+    }
+
+    fn node_removed(&mut self, key: (i32, &str)) {
+        self.changed = true;
+        let line = format!("removed {}", key_label(key, &self.options));
+        self.step(line);
+    }
+
+    fn successor_swapped(&mut self, key: (i32, &str), successor: (i32, &str)) {
+        let line = format!("replace {} by its successor {}", key_label(key, &self.options),
+                           key_label(successor, &self.options));
+        self.step(line);
+    }
+
+    fn rotated_left(&mut self, node: (i32, &str), _child: (i32, &str), subtree: &Node) {
+        let line = format!("rotate left at {}:", key_label(node, &self.options));
+        self.step(line);
+        push_indented(&mut self.text, &subtree.render_ascii_with(&self.options));
+    }
+
+    fn rotated_right(&mut self, node: (i32, &str), _child: (i32, &str), subtree: &Node) {
+        let line = format!("rotate right at {}:", key_label(node, &self.options));
+        self.step(line);
+        push_indented(&mut self.text, &subtree.render_ascii_with(&self.options));
+    }
+
+    /// The subtree after the repair was already shown with the last rotation.
+    fn rebalanced(&mut self, rotation: Rotation, pivot: (i32, &str), _subtree: &Node) {
+        let line = format!("{} at {}", rotation, key_label(pivot, &self.options));
+        self.step(line);
+    }
+}
+
+/// Append every line of the text indented by four spaces.
+///
+/// # Arguments
+///
+/// * `out` - The output to which the text should be appended
+/// * `text` - Lines which all end in a newline
+fn push_indented(out: &mut String, text: &str) {
+    for line in text.lines() {
+        out.push_str("    ");
+        out.push_str(line);
+        out.push('\n');
+    }
+}
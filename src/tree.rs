@@ -1,8 +1,32 @@
-use std::borrow::Borrow;
 use std::cmp;
 use std::cmp::{Ord, Ordering};
+use std::fmt;
+use std::fmt::Write as _;
+use std::io;
+
+mod ascii;
+mod dot;
+#[cfg(feature = "serde")]
+pub mod serde_impl;
+mod observer;
+mod query;
+mod set_ops;
+mod snapshot;
+mod stats;
+mod validate;
+
+pub use ascii::AsciiOptions;
+pub(crate) use ascii::key_label;
+pub use observer::{NoopObserver, Rotation, TreeObserver};
+pub use snapshot::{SnapshotError, SNAPSHOT_MAGIC, SNAPSHOT_VERSION};
+#[cfg(feature = "stats")]
+pub use stats::Stats;
+pub use validate::InvariantError;
+pub(crate) use snapshot::Crc32;
+use stats::{OpCounters, Recorder};
 
 /// Node representation
+#[derive(Clone)]
 pub struct Node{
     /// Left child of this node
     left: Option<Box<Node>>,
@@ -14,19 +38,68 @@ pub struct Node{
     name: String,
     /// Height of this node
     height: i32,
+    /// Number of nodes in the subtree of this node, including itself
+    size: usize,
 }
 
-/// AVL self balanced binary tree representation
+/// AVL self balanced binary tree representation.
+/// Entries are ordered by age first and by name second, see `compare`.
+/// A clone has the same shape as the original and starts with a copy of its statistics.
+#[derive(Clone)]
 pub struct Tree{
     /// Root node of the tree
-    root: Option<Box<Node>>
+    root: Option<Box<Node>>,
+    /// Operation statistics, empty without the `stats` feature
+    stats: Recorder,
+}
+
+/// In-order iterator over the data of a tree.
+/// Yields `(age, name)` pairs sorted by age first and by name second.
+pub struct Iter<'a> {
+    /// Nodes whose left subtree has been visited, but the node itself not yet
+    stack: Vec<&'a Node>,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Tree::new()
+    }
 }
 
 impl Tree {
     /// Create a new tree.
     /// Returns empty tree.
     pub fn new() -> Tree {
-        Tree { root: None }
+        Tree::from_root(None)
+    }
+
+    /// Create a tree with the given root node.
+    fn from_root(root: Option<Box<Node>>) -> Tree {
+        Tree { root, stats: Recorder::default() }
+    }
+
+    /// Build a tree from data which is already sorted by age and name and contains no duplicates.
+    /// Returns balanced tree containing all the given data.
+    ///
+    /// # Argument
+    ///
+    /// * `entries` - Strictly increasing `(age, name)` pairs
+    ///
+    /// # Discussion
+    ///
+    /// The tree is built in O(n) by always picking the middle entry as the root of a subtree.
+    /// The caller is responsible for the ordering, use `is_strictly_sorted` to check it.
+    pub fn from_sorted(entries: Vec<(i32, String)>) -> Tree {
+        let mut entries = entries.into_iter();
+        let len = entries.len();
+        Tree::from_root(build_sorted(&mut entries, len))
+    }
+
+    /// Returns an iterator over the data of the tree in sorted order.
+    pub fn iter(&self) -> Iter<'_> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
     }
 
     /// Print a tree in the following format:
@@ -35,11 +108,41 @@ impl Tree {
     ///     <data>             {<age:int>: "<name:string>"}
     ///     <left>, <right>:   The same format as the root node. When a child node is NULL, the string NULL is to be printed.
     pub fn print(&self) {
-        match self.root.borrow() {
-            Some(node) => node.print(),
-            None => print!("null")
-        }
-        println!()
+        println!("{}", self)
+    }
+
+    /// Write the tree to the given writer in the same format as `print`, without the trailing newline.
+    ///
+    /// # Argument
+    ///
+    /// * `writer` - A writer to which the tree should be written
+    pub fn write_json<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{}", self)
+    }
+
+    /// Write the tree to the given writer as indented JSON, without the trailing newline.
+    ///
+    /// # Argument
+    ///
+    /// * `writer` - A writer to which the tree should be written
+    ///
+    /// # Discussion
+    ///
+    /// Every node and null child is written on its own line, indented by two spaces per level:
+    ///
+    /// ```text
+    /// [
+    ///   {"42": "Peter"},
+    ///   [
+    ///     {"21": "Joanna"},
+    ///     null,
+    ///     null
+    ///   ],
+    ///   null
+    /// ]
+    /// ```
+    pub fn write_json_pretty<W: io::Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "{:#}", self)
     }
 
     /// Find an item in the tree. Return true if found, or false if not.
@@ -49,12 +152,22 @@ impl Tree {
     /// * `age` - Age of the data of the newly inserted node
     /// * `name` - Name of the data of the newly inserted node
     pub fn find(&self, age: i32, name: String) -> bool {
-        match &self.root{
-            &Some(ref node) => {
-                node.find(age, name)
-            }
-            &None => false
+        let mut counters = OpCounters::default();
+        let mut node = self.root.as_deref();
+        let mut found = false;
+        while let Some(n) = node {
+            counters.visit();
+            node = match compare(age, &name, n) {
+                Ordering::Equal => {
+                    found = true;
+                    break;
+                }
+                Ordering::Less => n.left.as_deref(),
+                Ordering::Greater => n.right.as_deref(),
+            };
         }
+        self.stats.record_find(&counters);
+        found
     }
 
     /// Insert a new data point into the tree
@@ -64,10 +177,26 @@ impl Tree {
     /// * `age` - Age of the data of the newly to be inserted node
     /// * `name` - Name of the data of the newly to be inserted node
     pub fn insert(&mut self, age: i32, name: String) {
+        self.insert_with(age, name, &mut NoopObserver)
+    }
+
+    /// Insert a new data point into the tree and report the structural changes to the observer
+    ///
+    /// # Arguments
+    ///
+    /// * `age` - Age of the data of the newly to be inserted node
+    /// * `name` - Name of the data of the newly to be inserted node
+    /// * `observer` - Observer which is told about the inserted node and the rotations
+    pub fn insert_with<O: TreeObserver + ?Sized>(&mut self, age: i32, name: String, observer: &mut O) {
+        let mut counters = OpCounters::default();
         match self.root.take(){
-            Some(node) => self.root = node_insert(node, age, name),
-            None => self.root = Some(Box::new(Node::new(age, name.clone())))
+            Some(node) => self.root = node_insert(node, age, name, &mut counters, observer),
+            None => {
+                observer.node_inserted((age, &name));
+                self.root = Some(Box::new(Node::new(age, name)))
+            }
         }
+        self.stats.record_insert(&counters, height(&self.root));
     }
 
     ///Remove a data point from a tree
@@ -77,31 +206,74 @@ impl Tree {
     /// * `age` - Age of the data of the node to be deleted
     /// * `name` - Name of the data of the node to be deleted
     pub fn erase(&mut self, age: i32, name: String) {
-        match self.root.take(){
-            Some(root) => {
-                self.root = node_delete(root, age, name);
-            }
-            None => return
+        self.erase_with(age, name, &mut NoopObserver)
+    }
+
+    /// Remove a data point from a tree and report the structural changes to the observer
+    ///
+    /// # Arguments
+    ///
+    /// * `age` - Age of the data of the node to be deleted
+    /// * `name` - Name of the data of the node to be deleted
+    /// * `observer` - Observer which is told about the removed node, the successor swap and the rotations
+    pub fn erase_with<O: TreeObserver + ?Sized>(&mut self, age: i32, name: String, observer: &mut O) {
+        let mut counters = OpCounters::default();
+        if let Some(root) = self.root.take() {
+            self.root = node_delete(root, age, name, &mut counters, observer);
         }
+        self.stats.record_erase(&counters);
     }
 
     /// Delete an entire tree. This will delete the passed Node and all children below it
     pub fn delete(&mut self) {
-        match self.root.take() {
-            Some(node) => {
-                self.root = tree_delete(node)
-            }
-            None => return
+        if let Some(mut node) = self.root.take() {
+            self.root = tree_delete(&mut node)
         }
     }
 }
 
+impl fmt::Debug for Tree {
+    /// Format the tree as its root node, the statistics are left out.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tree").field("root", &self.root).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a Tree {
+    type Item = (i32, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> Iter<'a> {
+    /// Push the given node and all of its left descendants onto the stack.
+    fn push_left(&mut self, mut node: Option<&'a Node>) {
+        while let Some(n) = node {
+            self.stack.push(n);
+            node = n.left.as_deref();
+        }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (i32, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((node.age, node.name.as_str()))
+    }
+}
+
 impl Node {
 
     /// Create a new node.
     /// Returns newly created node.
     pub fn new(age: i32, name: String) -> Node {
-        Node {age, name, height : 1, left : None, right : None }
+        Node {age, name, height : 1, size : 1, left : None, right : None }
     }
 
     /// Return immutable reference to the age data of the node
@@ -119,8 +291,10 @@ impl Node {
     /// # Discussion
     ///
     /// The height is updated by picking the maximum height of ots child and incrementing it by 1.
+    /// The size of the subtree is updated along with it, as both change whenever a child changes.
     pub fn update_height(&mut self) {
-        self.height = cmp::max(height(&self.right),height(&self.left)) + 1
+        self.height = cmp::max(height(&self.right),height(&self.left)) + 1;
+        self.size = size(&self.left) + size(&self.right) + 1;
     }
 
     /// Find an item in the tree of this node. Return true if found, or false if not.
@@ -130,28 +304,18 @@ impl Node {
     /// * `age` - Age of the data of the node to be searched
     /// * `name` - Name of the data of the node to be searched
     pub fn find(&self, age: i32, name: String) -> bool {
-        match age.cmp(self.age()) {
-            Ordering::Equal => {
-                match age.cmp(self.age()) {
-                    Ordering::Equal => true,
-                    Ordering::Less | Ordering::Greater => {
-                        match &self.left {
-                            &Some(ref node) => node.find(age, name),
-                            &None => false
-                        }
-                    }
-                }
-            }
+        match compare(age, &name, self) {
+            Ordering::Equal => true,
             Ordering::Less  => {
-                match &self.left {
-                    &Some(ref node) => node.find(age, name),
-                    &None => false
+                match self.left {
+                    Some(ref node) => node.find(age, name),
+                    None => false
                 }
             },
             Ordering::Greater => {
-                match &self.right {
-                    &Some(ref node) => node.find(age, name),
-                    &None => false
+                match self.right {
+                    Some(ref node) => node.find(age, name),
+                    None => false
                 }
             }
         }
@@ -163,35 +327,173 @@ impl Node {
     ///     <data>             {<age:int>: "<name:string>"}
     ///     <left>, <right>:   The same format as the root node. When a child node is NULL, the string NULL is to be printed.
     pub fn print(&self) {
-        print!("[");
-        print!("{{\"{}\":\"{}\"}},", self.age, self.name);
-        if let &Some(ref left) = &self.left {
-            left.print();
+        print!("{}", self);
+    }
+
+    /// Format the node and its children indented by two spaces per level.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Formatter to which the node should be written
+    /// * `depth` - Depth of this node in the tree, the root has depth 0
+    fn fmt_pretty(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth + 1);
+        writeln!(f, "[")?;
+        write!(f, "{}{{\"{}\": ", indent, self.age)?;
+        write_json_string(f, &self.name)?;
+        write!(f, "}}")?;
+        for child in [&self.left, &self.right].iter() {
+            write!(f, ",\n{}", indent)?;
+            match child {
+                Some(node) => node.fmt_pretty(f, depth + 1)?,
+                None => write!(f, "null")?
+            }
+        }
+        write!(f, "\n{}]", &indent[2..])
+    }
+}
+
+impl fmt::Display for Tree {
+    /// Format the tree in the format described at `Tree::print`.
+    /// With the alternate flag (`{:#}`) the tree is indented, see `Tree::write_json_pretty`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.root {
+            Some(ref node) => fmt::Display::fmt(node, f),
+            None => write!(f, "null")
+        }
+    }
+}
+
+impl fmt::Display for Node {
+    /// Format the node and its children in the format described at `Node::print`.
+    /// With the alternate flag (`{:#}`) the node is indented, see `Tree::write_json_pretty`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return self.fmt_pretty(f, 0);
+        }
+        write!(f, "[")?;
+        write!(f, "{{\"{}\":", self.age)?;
+        write_json_string(f, &self.name)?;
+        write!(f, "}},")?;
+        if let Some(ref left) = self.left {
+            write!(f, "{}", left)?;
         }else {
-            print!("null");
+            write!(f, "null")?;
         }
-        print!(",");
-        if let &Some(ref right) = &self.right {
-            right.print();
+        write!(f, ",")?;
+        if let Some(ref right) = self.right {
+            write!(f, "{}", right)?;
         }else {
-            print!("null");
+            write!(f, "null")?;
         }
-        print!("]");
+        write!(f, "]")
+    }
+}
+
+/// Write the given string as a JSON string, escaped according to RFC 8259.
+///
+/// # Arguments
+///
+/// * `f` - Formatter to which the string should be written
+/// * `s` - The string which should be written
+///
+/// # Discussion
+///
+/// Quotes, backslashes and control characters are escaped, all other characters are written as they are.
+pub(crate) fn write_json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{8}' => f.write_str("\\b")?,
+            '\u{c}' => f.write_str("\\f")?,
+            c if c < '\u{20}' => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?
+        }
+    }
+    f.write_char('"')
+}
+
+impl fmt::Debug for Node {
+    /// Format the node with its data and height first, followed by its children.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("age", &self.age)
+            .field("name", &self.name)
+            .field("height", &self.height)
+            .field("left", &self.left)
+            .field("right", &self.right)
+            .finish()
     }
 }
 
+/// Compare the given data with the data of the given node.
+/// Data is ordered by age first and by name second.
+///
+/// # Arguments
+///
+/// * `age` - Age of the data to be compared
+/// * `name` - Name of the data to be compared
+/// * `node` - A node with which the data should be compared
+///
+/// # Discussion
+///
+/// `find`, `insert` and `erase` all descend by this order. Comparing the ages only sent every
+/// entry of an equal age to the left, where `find` and `erase` of another name missed it, and
+/// the in-order sequence which `from_sorted`, snapshots and serde rely on would not be sorted.
+fn compare(age: i32, name: &str, node: &Node) -> Ordering {
+    age.cmp(&node.age).then_with(|| name.cmp(node.name.as_str()))
+}
+
+/// Returns true if every `(age, name)` pair is strictly smaller than the next one.
+///
+/// # Argument
+///
+/// * `entries` - Data which should be checked
+pub fn is_strictly_sorted(entries: &[(i32, String)]) -> bool {
+    entries.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Build a balanced tree from the next `len` entries of a sorted iterator.
+/// Returns the root node of the tree.
+///
+/// # Arguments
+///
+/// * `entries` - Iterator over sorted data
+/// * `len` - Number of entries to be taken from the iterator
+///
+/// # Discussion
+///
+/// The left half is built first, so the entries are consumed in order.
+/// Both halves differ by at most one entry, thus the result is always AVL balanced.
+fn build_sorted<I: Iterator<Item = (i32, String)>>(entries: &mut I, len: usize) -> Option<Box<Node>> {
+    if len == 0 {
+        return None;
+    }
+    let left = build_sorted(entries, len / 2);
+    let (age, name) = entries.next().expect("error in build_sorted");
+    let mut node = Box::new(Node::new(age, name));
+    node.left = left;
+    node.right = build_sorted(entries, len - len / 2 - 1);
+    node.update_height();
+    Some(node)
+}
 
 /// Delete an entire tree. This will delete the passed Node and all children below it
 ///
 /// # Argument
 ///
 /// * `node` - A node for which tree should be deleted
-fn tree_delete (mut node: Box<Node>) -> Option<Box<Node>>{
-    if let Some(left) = node.left.take() {
-        return tree_delete(left);
+fn tree_delete (node: &mut Node) -> Option<Box<Node>>{
+    if let Some(mut left) = node.left.take() {
+        return tree_delete(&mut left);
     }
-    if let Some(right) = node.right.take() {
-        return tree_delete(right);
+    if let Some(mut right) = node.right.take() {
+        return tree_delete(&mut right);
     }
     None
 }
@@ -204,50 +506,51 @@ fn tree_delete (mut node: Box<Node>) -> Option<Box<Node>>{
 /// * `node` - A node which is the root of the tree where the node(with the given age and name) should be deleted
 /// * `age` - Age of the data of the node to be deleted
 /// * `name` - Name of the data of the node to be deleted
+/// * `counters` - Counters of the erase, see `balance`
+/// * `observer` - Observer of the structural changes
 ///
 /// # Discussion
 ///
 /// The balance of the tree is automatically maintained after the deletion.
 /// Thus, returned root node of the tree is already correctly balanced.
-fn node_delete (mut node: Box<Node>, age: i32, name: String) -> Option<Box<Node>>{
-    match age.cmp(node.age()){
+fn node_delete<O: TreeObserver + ?Sized> (mut node: Box<Node>, age: i32, name: String, counters: &mut OpCounters,
+                                          observer: &mut O) -> Option<Box<Node>>{
+    observer.node_visited((node.age, &node.name));
+    match compare(age, &name, &node) {
         Ordering::Less => {
             if let Some(left) = node.left.take() {
-                node.left = node_delete(left, age, name);
+                node.left = node_delete(left, age, name, counters, observer);
             }
         },
         Ordering::Greater => {
             if let Some(right) = node.right.take() {
-                node.right = node_delete(right, age, name);
+                node.right = node_delete(right, age, name, counters, observer);
             }
         },
         Ordering::Equal => {
-            if node.name != name {
-                if let Some(left) = node.left.take() {
-                    node.left = node_delete(left, age, name);
-                    return Some(node);
-                }
+            if node.right.is_none() && node.left.is_none(){
+                observer.node_removed((node.age, &node.name));
+                return None;
+            }else if node.right.is_some() && node.left.is_none(){
+                observer.node_removed((node.age, &node.name));
+                node = node.right.unwrap();
+            }else if node.left.is_some() && node.right.is_none(){
+                observer.node_removed((node.age, &node.name));
+                node = node.left.unwrap();
             }else{
-                if node.right.is_none() && node.left.is_none(){
-                    return None;
-                }else if node.right.is_some() && node.left.is_none(){
-                    node = node.right.unwrap();
-                }else if node.left.is_some() && node.right.is_none(){
-                    node = node.left.unwrap();
-                }else{
-                    if let Some(successor) = find_successor(&node) {
-                        let s_age = successor.age().clone();
-                        let s_name = successor.name().clone();
-                        node.age = s_age.clone();
-                        node.name = s_name.clone();
-                        node.right = node_delete(node.right.unwrap(), s_age, s_name);
-                    }
+                if let Some(successor) = find_successor(&node) {
+                    let s_age = *successor.age();
+                    let s_name = successor.name().clone();
+                    observer.successor_swapped((node.age, &node.name), (s_age, &s_name));
+                    node.age = s_age;
+                    node.name = s_name.clone();
+                    node.right = node_delete(node.right.unwrap(), s_age, s_name, counters, observer);
                 }
             }
         }
     }
     node.update_height();
-    balance(node)
+    balance(node, counters, observer)
 }
 
 /// Insert new node(with the given age and name) into appropriate place in the tree of the given node.
@@ -258,41 +561,41 @@ fn node_delete (mut node: Box<Node>, age: i32, name: String) -> Option<Box<Node>
 /// * `node` - A node which is the root of the tree into which we want to insert new node
 /// * `age` - Age of the data of the newly to be inserted node
 /// * `name` - Name of the data of the newly to be inserted node
+/// * `counters` - Counters of the insert, see `balance`
+/// * `observer` - Observer of the structural changes
 ///
 /// # Discussion
 ///
 /// The balance of the tree is automatically maintained after the insertion.
 /// Thus, returned root node of the tree is already correctly balanced.
-fn node_insert (mut node: Box<Node>, age: i32, name: String) -> Option<Box<Node>> {
-    match age.cmp(node.age()) {
+fn node_insert<O: TreeObserver + ?Sized> (mut node: Box<Node>, age: i32, name: String, counters: &mut OpCounters,
+                                          observer: &mut O) -> Option<Box<Node>> {
+    observer.node_visited((node.age, &node.name));
+    match compare(age, &name, &node) {
         Ordering::Equal => {
-            match name.cmp(node.name()) {
-                Ordering::Equal => {
-                    return Some(node)
-                },
-                Ordering::Less | Ordering:: Greater => {
-                    match node.left.take() {
-                        Some(n) => node.left = node_insert(n, age, name.clone()),
-                        None =>  node.left = Some(Box::new(Node::new(age, name.clone())))
-                    }
-                }
-            }
+            return Some(node)
         },
         Ordering::Less => {
             match node.left.take() {
-                Some(n) => node.left = node_insert(n, age, name.clone()),
-                None =>  node.left = Some(Box::new(Node::new(age, name.clone())))
+                Some(n) => node.left = node_insert(n, age, name.clone(), counters, observer),
+                None => {
+                    observer.node_inserted((age, &name));
+                    node.left = Some(Box::new(Node::new(age, name)))
+                }
             }
         }
         Ordering::Greater => {
             match node.right.take() {
-                Some(n) => node.right = node_insert(n, age, name.clone()),
-                None => node.right = Some(Box::new(Node::new(age, name.clone())))
+                Some(n) => node.right = node_insert(n, age, name.clone(), counters, observer),
+                None => {
+                    observer.node_inserted((age, &name));
+                    node.right = Some(Box::new(Node::new(age, name)))
+                }
             }
         }
     }
     node.update_height();
-    balance(node)
+    balance(node, counters, observer)
 }
 
 /// Returns successor for the given node.
@@ -304,8 +607,8 @@ fn node_insert (mut node: Box<Node>, age: i32, name: String) -> Option<Box<Node>
 /// # Discussion
 ///
 /// If no successor is found, the node itself is returned.
-fn find_successor (node: &Box<Node>) -> Option<&Box<Node>> {
-    return match node.right {
+fn find_successor (node: &Node) -> Option<&Node> {
+    match node.right {
         Some(ref right) => {
             find_leftmost(right)
         },
@@ -322,7 +625,7 @@ fn find_successor (node: &Box<Node>) -> Option<&Box<Node>> {
 /// # Discussion
 ///
 /// If no left child is found, the node itself is returned.
-fn find_leftmost (node: &Box<Node>) -> Option<&Box<Node>> {
+fn find_leftmost (node: &Node) -> Option<&Node> {
     match node.left {
         Some(ref left) => find_leftmost(left),
         None => Some(node)
@@ -331,28 +634,34 @@ fn find_leftmost (node: &Box<Node>) -> Option<&Box<Node>> {
 
 /// Rebalanced the subtree if needed and return the new/old balanced sub tree.
 ///
-/// # Argument
+/// # Arguments
 ///
 /// * `node` - A node for which balancing should be performed
-fn balance (mut node: Box<Node>) -> Option<Box<Node>> {
+/// * `counters` - Counters to which the single and double rotations are added
+/// * `observer` - Observer which is told about every rotation
+fn balance<O: TreeObserver + ?Sized> (mut node: Box<Node>, counters: &mut OpCounters, observer: &mut O) -> Option<Box<Node>> {
     let balance_factor = calc_balance(&node);
 
     if balance_factor > 1 {
         if get_balance(&node.left) >= 0 {
-            return rotate_right(node);
+            counters.single_rotation();
+            return report_rebalance(Rotation::LeftLeft, rotate_right(node, observer), observer);
         }else{
             if let Some(left) = node.left.take(){
-                node.left = rotate_left(left);
-                return rotate_right(node);
+                counters.double_rotation();
+                node.left = rotate_left(left, observer);
+                return report_rebalance(Rotation::LeftRight, rotate_right(node, observer), observer);
             }
         }
     }else if balance_factor < -1  {
         if get_balance(&node.right) <= 0 {
-            return rotate_left(node);
+            counters.single_rotation();
+            return report_rebalance(Rotation::RightRight, rotate_left(node, observer), observer);
         }else{
             if let Some(right) = node.right.take(){
-                node.right = rotate_right(right);
-                return rotate_left(node);
+                counters.double_rotation();
+                node.right = rotate_right(right, observer);
+                return report_rebalance(Rotation::RightLeft, rotate_left(node, observer), observer);
             }
         }
     }
@@ -360,16 +669,52 @@ fn balance (mut node: Box<Node>) -> Option<Box<Node>> {
     Some(node)
 }
 
+/// Tell the observer about a repaired node and return the balanced subtree.
+///
+/// # Arguments
+///
+/// * `rotation` - The case which was repaired
+/// * `subtree` - The balanced subtree returned by the last rotation
+/// * `observer` - Observer which is told about the repair
+///
+/// # Discussion
+///
+/// The pivot is the node which was unbalanced. The last rotation moved it below the new root
+/// of the subtree: to the right for `LL` and `LR`, to the left for `RR` and `RL`.
+fn report_rebalance<O: TreeObserver + ?Sized> (rotation: Rotation, subtree: Option<Box<Node>>, observer: &mut O) -> Option<Box<Node>> {
+    if let Some(ref root) = subtree {
+        let pivot = match rotation {
+            Rotation::LeftLeft | Rotation::LeftRight => &root.right,
+            Rotation::RightRight | Rotation::RightLeft => &root.left,
+        };
+        if let Some(pivot) = pivot {
+            observer.rebalanced(rotation, (pivot.age, &pivot.name), root);
+        }
+    }
+    subtree
+}
+
 /// If given node is Some, it returns height of the node, otherwise 0 is returned.
 ///
 /// # Argument
 ///
 /// * `node` - A node for which its height should be returned.
 pub fn height (node: &Option<Box<Node>>) -> i32{
-    if node.is_none() {
-        return 0;
-    }else{
-        return node.as_ref().unwrap().height;
+    match node {
+        Some(node) => node.height,
+        None => 0
+    }
+}
+
+/// If given node is Some, it returns the number of nodes in its subtree, otherwise 0 is returned.
+///
+/// # Argument
+///
+/// * `node` - A node for which the size of its subtree should be returned.
+pub fn size (node: &Option<Box<Node>>) -> usize{
+    match node {
+        Some(node) => node.size,
+        None => 0
     }
 }
 
@@ -378,7 +723,7 @@ pub fn height (node: &Option<Box<Node>>) -> i32{
 /// # Argument
 ///
 /// * `node` - A node for which its balance factor should be returned.
-pub fn calc_balance (node: &Box<Node>) -> i32{
+pub fn calc_balance (node: &Node) -> i32{
     height(&node.left) - height(&node.right)
 }
 
@@ -400,17 +745,18 @@ pub fn get_balance (node: &Option<Box<Node>>) -> i32{
 /// # Arguments
 ///
 /// * `y` - A node on which the rotation should be performed
+/// * `observer` - Observer which is told about the rotation
 ///
 /// # Visual illustration
 ///
-/// ```
+/// ```text
 ///     (y)           (x)
 ///     / \           / \
 ///   (x)  c   ==>   a  (y)
 ///   / \               / \
 ///  a   b             b   c
 /// ```
-fn rotate_right (mut y : Box<Node>) -> Option<Box<Node>>{
+fn rotate_right<O: TreeObserver + ?Sized> (mut y : Box<Node>, observer: &mut O) -> Option<Box<Node>>{
     let mut x = y.left.take().expect("error in rotate_right");
     let b = x.right.take();
 
@@ -419,6 +765,9 @@ fn rotate_right (mut y : Box<Node>) -> Option<Box<Node>>{
     x.right = Some(y);
     x.update_height();
 
+    let y = x.right.as_deref().expect("error in rotate_right");
+    observer.rotated_right((y.age, &y.name), (x.age, &x.name), &x);
+
     Some(x)
 }
 
@@ -426,17 +775,18 @@ fn rotate_right (mut y : Box<Node>) -> Option<Box<Node>>{
 ///
 /// # Arguments
 /// * 'y' - A node on which the rotation should be performed
+/// * `observer` - Observer which is told about the rotation
 ///
 /// # Visual illustration
 ///
-/// ```
+/// ```text
 ///    (x)            (y)
 ///    / \            / \
 ///   a  (y)   ==>  (x)  c
 ///      / \        / \
 ///     b   c      a   b
 /// ```
-fn rotate_left (mut x : Box<Node>) -> Option<Box<Node>>{
+fn rotate_left<O: TreeObserver + ?Sized> (mut x : Box<Node>, observer: &mut O) -> Option<Box<Node>>{
     let mut y = x.right.take().expect("error in rotate_left");
     let b = y.left.take();
 
@@ -445,5 +795,8 @@ fn rotate_left (mut x : Box<Node>) -> Option<Box<Node>>{
     y.left = Some(x);
     y.update_height();
 
+    let x = y.left.as_deref().expect("error in rotate_left");
+    observer.rotated_left((x.age, &x.name), (y.age, &y.name), &y);
+
     Some(y)
-}
\ No newline at end of file
+}
@@ -0,0 +1,135 @@
+//! Terminal rendering of the tree structure with box-drawing characters.
+
+use super::{calc_balance, Node, Tree};
+
+/// Options for `Tree::render_ascii_with`.
+pub struct AsciiOptions {
+    /// Show the height and balance factor of every node
+    pub show_details: bool,
+    /// Names longer than this many characters are truncated and end in `…`
+    pub max_name_len: usize,
+}
+
+impl Default for AsciiOptions {
+    fn default() -> Self {
+        AsciiOptions { show_details: false, max_name_len: 16 }
+    }
+}
+
+impl Tree {
+    /// Returns the tree drawn with box-drawing characters, using the default `AsciiOptions`.
+    pub fn render_ascii(&self) -> String {
+        self.render_ascii_with(&AsciiOptions::default())
+    }
+
+    /// Returns the tree drawn with box-drawing characters.
+    ///
+    /// # Argument
+    ///
+    /// * `options` - Options which control what is shown for every node
+    ///
+    /// # Discussion
+    ///
+    /// Every node is shown on its own line as `{age: name}`, with its children below it.
+    /// The left child is drawn first and the right child second. When only one child
+    /// is present, the missing one is drawn as `·` so the sides can be told apart:
+    ///
+    /// ```text
+    /// {5: Frank}
+    /// ├── {1: Peter}
+    /// │   ├── ·
+    /// │   └── {2: Mark}
+    /// └── {42: Peter}
+    ///     ├── {21: Joanna}
+    ///     └── {83: Margaretha}
+    /// ```
+    pub fn render_ascii_with(&self, options: &AsciiOptions) -> String {
+        match self.root {
+            Some(ref node) => node.render_ascii_with(options),
+            None => "·\n".to_string()
+        }
+    }
+}
+
+impl Node {
+    /// Returns the subtree of this node drawn with box-drawing characters, see `Tree::render_ascii_with`.
+    ///
+    /// # Argument
+    ///
+    /// * `options` - Options which control what is shown for every node
+    pub fn render_ascii_with(&self, options: &AsciiOptions) -> String {
+        let mut out = label(self, options);
+        out.push('\n');
+        render_children(&mut out, self, "", options);
+        out
+    }
+}
+
+/// Append the children of the given node, each prefixed by the lines of its ancestors.
+///
+/// # Arguments
+///
+/// * `out` - The output to which the children should be appended
+/// * `node` - A node whose children should be rendered
+/// * `prefix` - The indentation of the children, continuing the lines of the ancestors
+/// * `options` - Options which control what is shown for every node
+fn render_children(out: &mut String, node: &Node, prefix: &str, options: &AsciiOptions) {
+    if node.left.is_none() && node.right.is_none() {
+        return;
+    }
+    for (child, last) in [(&node.left, false), (&node.right, true)].iter() {
+        out.push_str(prefix);
+        out.push_str(if *last { "└── " } else { "├── " });
+        match child {
+            Some(child) => {
+                out.push_str(&label(child, options));
+                out.push('\n');
+                let child_prefix = format!("{}{}", prefix, if *last { "    " } else { "│   " });
+                render_children(out, child, &child_prefix, options);
+            }
+            None => out.push_str("·\n")
+        }
+    }
+}
+
+/// Returns the `{age: name}` label of the given node.
+///
+/// # Arguments
+///
+/// * `node` - A node for which the label should be returned
+/// * `options` - Options which control what is shown for the node
+fn label(node: &Node, options: &AsciiOptions) -> String {
+    let mut label = key_label((node.age, &node.name), options);
+    if options.show_details {
+        label.push_str(&format!(" h={} bf={}", node.height, calc_balance(node)));
+    }
+    label
+}
+
+/// Returns the `{age: name}` label of the given data, without details.
+///
+/// # Arguments
+///
+/// * `key` - The `(age, name)` data for which the label should be returned
+/// * `options` - Options which control how the name is shown
+pub(crate) fn key_label(key: (i32, &str), options: &AsciiOptions) -> String {
+    format!("{{{}: {}}}", key.0, truncate(key.1, options.max_name_len))
+}
+
+/// Returns the name cut off at `max_len` characters, ending in `…` when it was cut off.
+/// Control characters are replaced by `?` so they cannot break the layout.
+///
+/// # Arguments
+///
+/// * `name` - The name which should be truncated
+/// * `max_len` - Maximum number of characters of the result
+fn truncate(name: &str, max_len: usize) -> String {
+    let clean = name.chars().map(|c| if c.is_control() { '?' } else { c });
+    if name.chars().count() <= max_len {
+        clean.collect()
+    } else {
+        let mut truncated: String = clean.take(max_len.saturating_sub(1)).collect();
+        truncated.push('…');
+        truncated
+    }
+}
@@ -0,0 +1,84 @@
+//! Graphviz DOT export of the tree structure.
+
+use super::{calc_balance, Node, Tree};
+
+impl Tree {
+    /// Returns the structure of the tree as a Graphviz DOT digraph.
+    ///
+    /// # Discussion
+    ///
+    /// Every node is labeled with its age and name, followed by its height and balance factor.
+    /// Missing children are drawn as points, so left and right children can always be told apart.
+    /// Render it with e.g. `dot -Tsvg tree.dot -o tree.svg`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph tree {\n");
+        dot.push_str("    graph [ordering=out];\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        let mut next_id = 0;
+        match self.root {
+            Some(ref node) => {
+                write_node(&mut dot, node, &mut next_id);
+            }
+            None => {
+                dot.push_str("    null0 [shape=point];\n");
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Write the given node, its children and the edges to them.
+/// Returns the identifier of the written node.
+///
+/// # Arguments
+///
+/// * `dot` - The DOT output to which the node should be appended
+/// * `node` - A node which should be written
+/// * `next_id` - Counter from which unique identifiers are taken
+fn write_node(dot: &mut String, node: &Node, next_id: &mut usize) -> String {
+    let id = format!("n{}", *next_id);
+    *next_id += 1;
+    dot.push_str(&format!("    {} [label=\"{}: {}\\nh={} bf={}\"];\n",
+                          id, node.age, escape(&node.name), node.height, calc_balance(node)));
+    for child in [&node.left, &node.right].iter() {
+        let child_id = match child {
+            Some(child) => write_node(dot, child, next_id),
+            None => {
+                let null_id = format!("null{}", *next_id);
+                *next_id += 1;
+                dot.push_str(&format!("    {} [shape=point];\n", null_id));
+                null_id
+            }
+        };
+        dot.push_str(&format!("    {} -> {};\n", id, child_id));
+    }
+    id
+}
+
+/// Escape a string for use inside a quoted DOT label.
+///
+/// # Argument
+///
+/// * `s` - The string which should be escaped
+///
+/// # Discussion
+///
+/// A line break starts a new line of the label. Other control characters are shown as
+/// `\u{<hex>}` and a backslash as `\\`, like `lexer::quote` writes them, so different
+/// names always get different labels.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            // DOT turns `\\` into a single backslash
+            '\\' => escaped.push_str("\\\\\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
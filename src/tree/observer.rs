@@ -0,0 +1,106 @@
+//! Hooks into the structural changes of a tree, see `Tree::insert_with` and `Tree::erase_with`.
+
+use std::fmt;
+
+use super::Node;
+
+/// The case of an unbalanced node which `balance` repairs, named after the path from the
+/// node to its tallest grandchild subtree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    /// The left subtree of the left child is too tall: single rotation to the right
+    LeftLeft,
+    /// The right subtree of the right child is too tall: single rotation to the left
+    RightRight,
+    /// The right subtree of the left child is too tall: the left child is rotated to the
+    /// left first, then the node to the right
+    LeftRight,
+    /// The left subtree of the right child is too tall: the right child is rotated to the
+    /// right first, then the node to the left
+    RightLeft,
+}
+
+impl fmt::Display for Rotation {
+    /// Format the case as `LL`, `RR`, `LR` or `RL`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Rotation::LeftLeft => "LL",
+            Rotation::RightRight => "RR",
+            Rotation::LeftRight => "LR",
+            Rotation::RightLeft => "RL",
+        })
+    }
+}
+
+/// Receives the structural changes made by an insert or erase, with the data of the nodes involved.
+/// Data is passed as `(age, name)`, like the items of `Tree::iter`. All methods do nothing by default.
+///
+/// # Discussion
+///
+/// An erase of a node with two children is reported as `successor_swapped` followed by
+/// `node_removed` of the successor, as the node takes the data of its successor and the
+/// node which held the successor is the one which is unlinked.
+pub trait TreeObserver {
+    /// A node was compared on the way down to the place of an insert or erase.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - Data of the visited node
+    fn node_visited(&mut self, _key: (i32, &str)) {}
+
+    /// A new node was linked into the tree.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - Data of the new node
+    fn node_inserted(&mut self, _key: (i32, &str)) {}
+
+    /// A node was unlinked from the tree.
+    ///
+    /// # Argument
+    ///
+    /// * `key` - Data of the removed node
+    fn node_removed(&mut self, _key: (i32, &str)) {}
+
+    /// A node with two children which is being erased took the data of its in-order successor.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Data which is erased
+    /// * `successor` - Data of the successor, which the node holds from now on
+    fn successor_swapped(&mut self, _key: (i32, &str), _successor: (i32, &str)) {}
+
+    /// A subtree was rotated to the left, see `rotate_left`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - Data of the root of the subtree, which became the left child of `child`
+    /// * `child` - Data of the right child of `node`, which became the root of the subtree
+    /// * `subtree` - The subtree after the rotation, with `child` at its root
+    fn rotated_left(&mut self, _node: (i32, &str), _child: (i32, &str), _subtree: &Node) {}
+
+    /// A subtree was rotated to the right, see `rotate_right`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - Data of the root of the subtree, which became the right child of `child`
+    /// * `child` - Data of the left child of `node`, which became the root of the subtree
+    /// * `subtree` - The subtree after the rotation, with `child` at its root
+    fn rotated_right(&mut self, _node: (i32, &str), _child: (i32, &str), _subtree: &Node) {}
+
+    /// `balance` repaired an unbalanced node, after the one or two rotations of the case were reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `rotation` - The case which was repaired
+    /// * `pivot` - Data of the node which was unbalanced
+    /// * `subtree` - The balanced subtree which took the place of the pivot
+    fn rebalanced(&mut self, _rotation: Rotation, _pivot: (i32, &str), _subtree: &Node) {}
+}
+
+/// Observer which ignores every change, used by `Tree::insert` and `Tree::erase`.
+/// Its calls are inlined away, so it costs nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopObserver;
+
+impl TreeObserver for NoopObserver {}
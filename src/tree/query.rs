@@ -0,0 +1,148 @@
+//! Order statistics and neighbour queries, using the subtree sizes stored in the nodes.
+
+use std::cmp::Ordering;
+use std::ops::RangeInclusive;
+
+use super::{compare, find_leftmost, height, size, Iter, Node, Tree};
+
+impl Tree {
+    /// Returns the number of entries in the tree.
+    pub fn len(&self) -> usize {
+        size(&self.root)
+    }
+
+    /// Returns true if the tree holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Returns the height of the tree, 0 for an empty tree.
+    pub fn height(&self) -> i32 {
+        height(&self.root)
+    }
+
+    /// Returns the smallest entry, or None if the tree is empty.
+    pub fn min(&self) -> Option<(i32, &str)> {
+        self.root.as_deref().and_then(find_leftmost).map(entry)
+    }
+
+    /// Returns the greatest entry, or None if the tree is empty.
+    pub fn max(&self) -> Option<(i32, &str)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some(entry(node))
+    }
+
+    /// Returns an iterator over the entries whose age is in the given range, in sorted order.
+    /// Finding the first entry takes O(log n), every following one O(1) on average.
+    ///
+    /// # Argument
+    ///
+    /// * `ages` - The smallest and the greatest age, both included
+    pub fn range(&self, ages: RangeInclusive<i32>) -> impl Iterator<Item = (i32, &str)> {
+        let (from, to) = ages.into_inner();
+        // The stack of the iterator holds the nodes of the path to the first entry which are not before it
+        let mut iter = Iter { stack: Vec::new() };
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            if n.age >= from {
+                iter.stack.push(n);
+                node = n.left.as_deref();
+            } else {
+                node = n.right.as_deref();
+            }
+        }
+        iter.take_while(move |&(age, _)| age <= to)
+    }
+
+    /// Returns the number of entries which are smaller than the given data.
+    /// For an entry in the tree this is its position in sorted order, counting from 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `age` - Age of the data
+    /// * `name` - Name of the data, which does not have to be in the tree
+    pub fn rank(&self, age: i32, name: &str) -> usize {
+        let mut rank = 0;
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            match compare(age, name, n) {
+                Ordering::Less => node = n.left.as_deref(),
+                Ordering::Equal => return rank + size(&n.left),
+                Ordering::Greater => {
+                    rank += size(&n.left) + 1;
+                    node = n.right.as_deref();
+                }
+            }
+        }
+        rank
+    }
+
+    /// Returns the entry at the given position in sorted order, or None if the tree holds fewer entries.
+    ///
+    /// # Argument
+    ///
+    /// * `k` - Position of the entry, counting from 0, so `select(rank(x))` is `x`
+    pub fn select(&self, mut k: usize) -> Option<(i32, &str)> {
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            let left = size(&n.left);
+            match k.cmp(&left) {
+                Ordering::Less => node = n.left.as_deref(),
+                Ordering::Equal => return Some(entry(n)),
+                Ordering::Greater => {
+                    k -= left + 1;
+                    node = n.right.as_deref();
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the smallest entry which is greater than the given data, or None if there is none.
+    ///
+    /// # Arguments
+    ///
+    /// * `age` - Age of the data
+    /// * `name` - Name of the data, which does not have to be in the tree
+    pub fn successor(&self, age: i32, name: &str) -> Option<(i32, &str)> {
+        let mut successor = None;
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            if compare(age, name, n) == Ordering::Less {
+                successor = Some(n);
+                node = n.left.as_deref();
+            } else {
+                node = n.right.as_deref();
+            }
+        }
+        successor.map(entry)
+    }
+
+    /// Returns the greatest entry which is smaller than the given data, or None if there is none.
+    ///
+    /// # Arguments
+    ///
+    /// * `age` - Age of the data
+    /// * `name` - Name of the data, which does not have to be in the tree
+    pub fn predecessor(&self, age: i32, name: &str) -> Option<(i32, &str)> {
+        let mut predecessor = None;
+        let mut node = self.root.as_deref();
+        while let Some(n) = node {
+            if compare(age, name, n) == Ordering::Greater {
+                predecessor = Some(n);
+                node = n.right.as_deref();
+            } else {
+                node = n.left.as_deref();
+            }
+        }
+        predecessor.map(entry)
+    }
+}
+
+/// Returns the `(age, name)` data of the node.
+fn entry(node: &Node) -> (i32, &str) {
+    (node.age, node.name.as_str())
+}
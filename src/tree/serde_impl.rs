@@ -0,0 +1,324 @@
+//! Serde support for `Tree`, enabled with the `serde` cargo feature.
+//!
+//! A tree has two representations:
+//!
+//! * sorted: a sequence of `[age, name]` entries in sorted order. This is what `Tree` itself
+//!   serializes to. It is compact and the tree is rebuilt in O(n) when it is loaded.
+//! * shape: the nested `[{"age":"name"}, left, right]` structure printed by `Tree::print`,
+//!   where an empty subtree is `null`. It keeps the exact structure of the tree.
+//!
+//! Deserializing a `Tree` reads the sorted form. Human readable formats such as JSON describe
+//! themselves well enough to also accept the shape form there. Wrap the tree in `Shape` to
+//! write or read the shape form explicitly in any format (e.g. bincode or MessagePack).
+//! A tree loaded from the shape form is checked to be sorted and AVL balanced, and may not be
+//! nested deeper than `MAX_DEPTH`.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, SerializeTuple, Serializer};
+
+use super::{is_strictly_sorted, InvariantError, Node, Tree};
+
+/// Deepest node accepted in the shape form, the root has depth 1.
+///
+/// # Discussion
+///
+/// An AVL tree of `n` nodes is at most about 1.44·log2(n) deep. The limit is 2·log2(n)+2 for the
+/// largest `n` which fits in a `usize`, so every tree which can be loaded stays below it, while a
+/// deeply nested document is rejected before it exhausts the stack.
+pub const MAX_DEPTH: usize = 2 * usize::BITS as usize + 2;
+
+/// Wrapper which (de)serializes a tree in the shape form.
+pub struct Shape(pub Tree);
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for entry in self {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tree, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(AnyVisitor)
+        } else {
+            deserializer.deserialize_seq(SortedVisitor)
+        }
+    }
+}
+
+impl Serialize for Shape {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0.root {
+            Some(ref node) => serializer.serialize_some(&NodeRef(node)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Shape, D::Error> {
+        let root = ChildSeed { depth: 1 }.deserialize(deserializer)?;
+        check_shape(root).map(Shape).map_err(de::Error::custom)
+    }
+}
+
+/// Borrowed node, serialized as `[data, left, right]`.
+struct NodeRef<'a>(&'a Node);
+
+/// The `{"age":"name"}` data of a node.
+struct DataRef<'a>(&'a Node);
+
+/// Reads a subtree, `null` or `[data, left, right]`, whose root is at the given depth.
+/// The nodes are not yet checked for order and balance.
+struct ChildSeed {
+    depth: usize,
+}
+
+/// The `(age, name)` data deserialized from a single entry map.
+struct Data(i32, String);
+
+/// First element of a sequence, which tells the two forms apart in self describing formats.
+enum Head {
+    /// `{"age":"name"}` data, the sequence is a shape form node
+    Data(i32, String),
+    /// `[age, name]` entry, the sequence is the sorted form
+    Entry(i32, String),
+}
+
+impl<'a> Serialize for NodeRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(3)?;
+        tuple.serialize_element(&DataRef(self.0))?;
+        tuple.serialize_element(&self.0.left.as_deref().map(NodeRef))?;
+        tuple.serialize_element(&self.0.right.as_deref().map(NodeRef))?;
+        tuple.end()
+    }
+}
+
+impl<'a> Serialize for DataRef<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.0.age, &self.0.name)?;
+        map.end()
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for ChildSeed {
+    type Value = Option<Box<Node>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Box<Node>>, D::Error> {
+        deserializer.deserialize_option(ChildVisitor { depth: self.depth })
+    }
+}
+
+impl<'de> Deserialize<'de> for Data {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Data, D::Error> {
+        deserializer.deserialize_map(DataVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Head {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Head, D::Error> {
+        deserializer.deserialize_any(HeadVisitor)
+    }
+}
+
+/// Visitor for the sorted form.
+struct SortedVisitor;
+
+/// Visitor for either form, used by self describing formats.
+struct AnyVisitor;
+
+/// Visitor for a subtree which may be empty.
+struct ChildVisitor {
+    depth: usize,
+}
+
+/// Visitor for `[data, left, right]`.
+struct NodeVisitor {
+    depth: usize,
+}
+
+/// Visitor for `{"age":"name"}`.
+struct DataVisitor;
+
+/// Visitor for the first element of a sequence.
+struct HeadVisitor;
+
+impl<'de> Visitor<'de> for SortedVisitor {
+    type Value = Tree;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sorted sequence of [age, name] entries")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Tree, A::Error> {
+        read_sorted(Vec::new(), seq)
+    }
+}
+
+impl<'de> Visitor<'de> for AnyVisitor {
+    type Value = Tree;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sorted sequence of [age, name] entries or a nested [data, left, right] tree")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Tree, E> {
+        Ok(Tree::new())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Tree, E> {
+        Ok(Tree::new())
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Tree, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Tree, A::Error> {
+        match seq.next_element::<Head>()? {
+            None => Ok(Tree::new()),
+            Some(Head::Entry(age, name)) => read_sorted(vec![(age, name)], seq),
+            Some(Head::Data(age, name)) => {
+                let node = read_children(Node::new(age, name), 1, &mut seq)?;
+                check_shape(Some(node)).map_err(de::Error::custom)
+            }
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for ChildVisitor {
+    type Value = Option<Box<Node>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("null or a [data, left, right] node")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Option<Box<Node>>, E> {
+        Ok(None)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Option<Box<Node>>, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Option<Box<Node>>, D::Error> {
+        if self.depth > MAX_DEPTH {
+            return Err(de::Error::custom(format_args!("the tree is nested deeper than {} nodes", MAX_DEPTH)));
+        }
+        deserializer.deserialize_tuple(3, NodeVisitor { depth: self.depth }).map(Some)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Box<Node>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a [data, left, right] node")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Box<Node>, A::Error> {
+        let Data(age, name) = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        read_children(Node::new(age, name), self.depth, &mut seq)
+    }
+}
+
+impl<'de> Visitor<'de> for DataVisitor {
+    type Value = Data;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map with a single age: name entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Data, A::Error> {
+        let (age, name) = map.next_entry::<i32, String>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(Data(age, name))
+    }
+}
+
+impl<'de> Visitor<'de> for HeadVisitor {
+    type Value = Head;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an [age, name] entry or an {age: name} map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Head, A::Error> {
+        let Data(age, name) = DataVisitor.visit_map(map)?;
+        Ok(Head::Data(age, name))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Head, A::Error> {
+        let age = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let name = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(3, &self));
+        }
+        Ok(Head::Entry(age, name))
+    }
+}
+
+/// Read the remaining `[age, name]` entries of the sorted form and build the tree.
+///
+/// # Arguments
+///
+/// * `entries` - Entries which were already read from the sequence
+/// * `seq` - The sequence from which the remaining entries should be read
+fn read_sorted<'de, A: SeqAccess<'de>>(mut entries: Vec<(i32, String)>, mut seq: A) -> Result<Tree, A::Error> {
+    while let Some(entry) = seq.next_element::<(i32, String)>()? {
+        entries.push(entry);
+    }
+    if !is_strictly_sorted(&entries) {
+        let index = entries.windows(2).position(|pair| pair[0] >= pair[1]).unwrap_or(0) + 1;
+        let (age, name) = &entries[index];
+        return Err(de::Error::custom(format_args!(
+            "entry {} {{{}: {:?}}} is not greater than the entry before it", index, age, name)));
+    }
+    Ok(Tree::from_sorted(entries))
+}
+
+/// Read the left and right child of a shape form node.
+/// Returns the node with its children and height set.
+///
+/// # Arguments
+///
+/// * `node` - A node whose data was already read
+/// * `depth` - Depth of the node, 1 for the root
+/// * `seq` - The sequence from which the children should be read
+fn read_children<'de, A: SeqAccess<'de>>(node: Node, depth: usize, seq: &mut A) -> Result<Box<Node>, A::Error> {
+    let mut node = Box::new(node);
+    let visitor = NodeVisitor { depth };
+    node.left = seq.next_element_seed(ChildSeed { depth: depth + 1 })?
+        .ok_or_else(|| de::Error::invalid_length(1, &visitor))?;
+    node.right = seq.next_element_seed(ChildSeed { depth: depth + 1 })?
+        .ok_or_else(|| de::Error::invalid_length(2, &visitor))?;
+    if seq.next_element::<IgnoredAny>()?.is_some() {
+        return Err(de::Error::invalid_length(4, &visitor));
+    }
+    node.update_height();
+    Ok(node)
+}
+
+/// Check that a tree loaded from the shape form is sorted and AVL balanced.
+/// Returns the tree, or the first violated invariant.
+///
+/// # Argument
+///
+/// * `root` - Root node of the loaded tree, with the heights computed while loading
+fn check_shape(root: Option<Box<Node>>) -> Result<Tree, InvariantError> {
+    let tree = Tree::from_root(root);
+    tree.validate()?;
+    Ok(tree)
+}
@@ -0,0 +1,87 @@
+//! Union, intersection and difference of trees, by merging their entries in sorted order.
+//!
+//! Both trees are walked once and the result is built with `Tree::from_sorted`, so every
+//! operation takes O(n + m) and returns a perfectly balanced tree.
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+use super::{Iter, Tree};
+
+impl Tree {
+    /// Returns a tree with the entries which are in this tree, the other tree or both.
+    ///
+    /// # Argument
+    ///
+    /// * `other` - The tree which is merged with this one
+    pub fn union(&self, other: &Tree) -> Tree {
+        merge(self, other, true, true, true)
+    }
+
+    /// Returns a tree with the entries which are in both this tree and the other tree.
+    ///
+    /// # Argument
+    ///
+    /// * `other` - The tree which is intersected with this one
+    pub fn intersection(&self, other: &Tree) -> Tree {
+        merge(self, other, false, true, false)
+    }
+
+    /// Returns a tree with the entries of this tree which are not in the other tree.
+    ///
+    /// # Argument
+    ///
+    /// * `other` - The tree with the entries which are left out
+    pub fn difference(&self, other: &Tree) -> Tree {
+        merge(self, other, true, false, false)
+    }
+
+    /// Returns true if both trees hold the same entries, whatever their shape.
+    ///
+    /// # Argument
+    ///
+    /// * `other` - The tree which is compared with this one
+    pub fn same_entries(&self, other: &Tree) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+/// Returns a tree with the entries of both trees which pass the given filter.
+///
+/// # Arguments
+///
+/// * `a` - The first tree
+/// * `b` - The second tree
+/// * `only_a` - Keep the entries which are only in `a`
+/// * `both` - Keep the entries which are in both trees
+/// * `only_b` - Keep the entries which are only in `b`
+fn merge(a: &Tree, b: &Tree, only_a: bool, both: bool, only_b: bool) -> Tree {
+    let mut a = a.iter().peekable();
+    let mut b = b.iter().peekable();
+    let mut entries = Vec::new();
+    loop {
+        let order = match (a.peek(), b.peek()) {
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => break,
+        };
+        let (entry, keep) = match order {
+            Ordering::Less => (next(&mut a), only_a),
+            Ordering::Greater => (next(&mut b), only_b),
+            Ordering::Equal => {
+                b.next();
+                (next(&mut a), both)
+            }
+        };
+        if keep {
+            entries.push((entry.0, entry.1.to_string()));
+        }
+    }
+    Tree::from_sorted(entries)
+}
+
+/// Returns the next entry of an iterator which is known not to be exhausted.
+fn next<'a>(iter: &mut Peekable<Iter<'a>>) -> (i32, &'a str) {
+    iter.next().expect("peeked entry")
+}
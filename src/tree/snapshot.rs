@@ -0,0 +1,237 @@
+//! Compact binary snapshot format of a tree.
+//!
+//! All integers are little endian. A snapshot consists of:
+//!
+//! | Field     | Size         | Description                                       |
+//! |-----------|--------------|---------------------------------------------------|
+//! | magic     | 4 bytes      | `AVLT`                                            |
+//! | version   | u16          | `SNAPSHOT_VERSION`                                |
+//! | count     | u64          | Number of records                                 |
+//! | records   | count times  | i32 age, u32 name length, UTF-8 name bytes        |
+//! | checksum  | u32          | CRC-32 (IEEE) of all the bytes before it          |
+//!
+//! The records are written in sorted order, so loading rebuilds the tree in O(n).
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::{is_strictly_sorted, Tree};
+
+/// Magic bytes at the start of every snapshot
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"AVLT";
+
+/// Version of the snapshot format written by `Tree::save_to`
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// Reasons why a snapshot could not be loaded
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading the snapshot failed
+    Io(io::Error),
+    /// The input ended before the snapshot was complete
+    Truncated,
+    /// The input does not start with `SNAPSHOT_MAGIC`
+    BadMagic([u8; 4]),
+    /// The snapshot was written in a format version this build does not know
+    UnsupportedVersion(u16),
+    /// The name of the record at the given index is not valid UTF-8
+    InvalidName(u64),
+    /// The record at the given index is not greater than the record before it
+    NotSorted(u64),
+    /// The stored checksum does not match the checksum of the data
+    ChecksumMismatch { stored: u32, computed: u32 },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "unable to read snapshot: {}", error),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::BadMagic(magic) => write!(f, "not a snapshot (magic bytes {:02x?})", magic),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {} (expected {})", version, SNAPSHOT_VERSION)
+            }
+            SnapshotError::InvalidName(index) => write!(f, "name of record {} is not valid UTF-8", index),
+            SnapshotError::NotSorted(index) => write!(f, "record {} is not greater than the record before it", index),
+            SnapshotError::ChecksumMismatch { stored, computed } => {
+                write!(f, "snapshot is corrupted (checksum {:08x}, expected {:08x})", computed, stored)
+            }
+        }
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            SnapshotError::Truncated
+        } else {
+            SnapshotError::Io(error)
+        }
+    }
+}
+
+impl Tree {
+    /// Write a snapshot of the tree to the given writer.
+    ///
+    /// # Argument
+    ///
+    /// * `writer` - A writer to which the snapshot should be written
+    pub fn save_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = ChecksumWriter { inner: writer, crc: Crc32::new() };
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.len() as u64).to_le_bytes())?;
+        for (age, name) in self {
+            writer.write_all(&age.to_le_bytes())?;
+            writer.write_all(&(name.len() as u32).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+        }
+        let checksum = writer.crc.finish();
+        writer.inner.write_all(&checksum.to_le_bytes())?;
+        writer.inner.flush()
+    }
+
+    /// Read a tree from a snapshot written by `save_to`.
+    /// Returns the loaded tree, or the reason why the snapshot is invalid.
+    ///
+    /// # Argument
+    ///
+    /// * `reader` - A reader from which the snapshot should be read
+    ///
+    /// # Discussion
+    ///
+    /// Nothing after the checksum is read, so a snapshot can be followed by other data.
+    pub fn load_from<R: Read>(reader: R) -> Result<Tree, SnapshotError> {
+        let mut reader = ChecksumReader { inner: reader, crc: Crc32::new() };
+
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic(magic));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let count = u64::from_le_bytes(read_array(&mut reader)?);
+
+        // The count is not trusted for the allocation, a corrupted one would be far too large
+        let mut entries = Vec::with_capacity(count.min(4096) as usize);
+        for index in 0..count {
+            let age = i32::from_le_bytes(read_array(&mut reader)?);
+            let len = u32::from_le_bytes(read_array(&mut reader)?);
+            let mut bytes = Vec::new();
+            (&mut reader).take(u64::from(len)).read_to_end(&mut bytes)?;
+            if bytes.len() != len as usize {
+                return Err(SnapshotError::Truncated);
+            }
+            let name = String::from_utf8(bytes).map_err(|_| SnapshotError::InvalidName(index))?;
+            entries.push((age, name));
+        }
+
+        let computed = reader.crc.finish();
+        let stored = u32::from_le_bytes(read_array(&mut reader.inner)?);
+        if stored != computed {
+            return Err(SnapshotError::ChecksumMismatch { stored, computed });
+        }
+        if !is_strictly_sorted(&entries) {
+            let index = entries.windows(2).position(|pair| pair[0] >= pair[1]).unwrap_or(0) + 1;
+            return Err(SnapshotError::NotSorted(index as u64));
+        }
+        Ok(Tree::from_sorted(entries))
+    }
+}
+
+/// Read exactly `N` bytes.
+///
+/// # Argument
+///
+/// * `reader` - A reader from which the bytes should be read
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Lookup table of the reflected CRC-32 (IEEE 802.3) polynomial
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// Returns the lookup table for `Crc32`.
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Running CRC-32 (IEEE 802.3) checksum, as used by zip and PNG.
+pub(crate) struct Crc32(u32);
+
+impl Crc32 {
+    pub(crate) fn new() -> Crc32 {
+        Crc32(0xFFFF_FFFF)
+    }
+
+    /// Add the given bytes to the checksum.
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ u32::from(byte)) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    /// Returns the checksum of all bytes added so far.
+    pub(crate) fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// Writer which keeps the checksum of everything written through it.
+struct ChecksumWriter<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reader which keeps the checksum of everything read through it.
+struct ChecksumReader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
@@ -0,0 +1,281 @@
+//! Operation statistics of a tree, enabled with the `stats` cargo feature.
+//!
+//! The recursive functions of the tree count what they do in an `OpCounters`, which is added
+//! to the totals of the tree once the operation is done. Without the feature both types are
+//! empty and all of their methods do nothing, so the counting compiles away.
+
+#[cfg(feature = "stats")]
+use std::fmt;
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+
+#[cfg(feature = "stats")]
+use super::{height, Tree};
+
+/// Snapshot of the statistics of a tree, see `Tree::stats`
+#[cfg(feature = "stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of calls to `insert`, including those of data which was already present
+    pub inserts: u64,
+    /// Number of calls to `erase`, including those of data which was not present
+    pub erases: u64,
+    /// Number of calls to `find`
+    pub finds: u64,
+    /// Number of single (LL or RR) rotations
+    pub single_rotations: u64,
+    /// Number of double (LR or RL) rotations
+    pub double_rotations: u64,
+    /// Number of rotations, single or double, done by all inserts
+    pub insert_rebalances: u64,
+    /// Most rotations done by a single insert
+    pub max_insert_rebalances: u64,
+    /// Number of rotations, single or double, done by all erases
+    pub erase_rebalances: u64,
+    /// Most rotations done by a single erase
+    pub max_erase_rebalances: u64,
+    /// Number of nodes compared by all finds
+    pub find_visits: u64,
+    /// Most nodes compared by a single find
+    pub max_find_visits: u64,
+    /// Greatest height the tree has had
+    pub max_height: i32,
+}
+
+#[cfg(feature = "stats")]
+impl Stats {
+    /// Returns the average number of rotations per insert.
+    pub fn rebalances_per_insert(&self) -> f64 {
+        average(self.insert_rebalances, self.inserts)
+    }
+
+    /// Returns the average number of rotations per erase.
+    pub fn rebalances_per_erase(&self) -> f64 {
+        average(self.erase_rebalances, self.erases)
+    }
+
+    /// Returns the average number of nodes compared per find.
+    pub fn visits_per_find(&self) -> f64 {
+        average(self.find_visits, self.finds)
+    }
+}
+
+#[cfg(feature = "stats")]
+impl fmt::Display for Stats {
+    /// Format the statistics as one `name: value` line per counter.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "inserts: {}", self.inserts)?;
+        writeln!(f, "erases: {}", self.erases)?;
+        writeln!(f, "finds: {}", self.finds)?;
+        writeln!(f, "single rotations: {}", self.single_rotations)?;
+        writeln!(f, "double rotations: {}", self.double_rotations)?;
+        writeln!(f, "rebalances per insert: {:.2} avg, {} max", self.rebalances_per_insert(), self.max_insert_rebalances)?;
+        writeln!(f, "rebalances per erase: {:.2} avg, {} max", self.rebalances_per_erase(), self.max_erase_rebalances)?;
+        writeln!(f, "nodes visited per find: {:.2} avg, {} max", self.visits_per_find(), self.max_find_visits)?;
+        writeln!(f, "max height: {}", self.max_height)
+    }
+}
+
+#[cfg(feature = "stats")]
+impl Tree {
+    /// Returns a snapshot of the statistics collected since the tree was created.
+    pub fn stats(&self) -> Stats {
+        let mut stats = self.stats.snapshot();
+        stats.max_height = stats.max_height.max(height(&self.root));
+        stats
+    }
+
+    /// Set all statistics back to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = Recorder::default();
+    }
+}
+
+/// Returns `total / count`, or 0 if nothing was counted.
+#[cfg(feature = "stats")]
+fn average(total: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        total as f64 / count as f64
+    }
+}
+
+/// Counters of a single insert, erase or find
+#[derive(Default)]
+pub(crate) struct OpCounters {
+    #[cfg(feature = "stats")]
+    single_rotations: u64,
+    #[cfg(feature = "stats")]
+    double_rotations: u64,
+    #[cfg(feature = "stats")]
+    visits: u64,
+}
+
+impl OpCounters {
+    /// Count a single rotation done by `balance`.
+    #[inline]
+    pub(crate) fn single_rotation(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.single_rotations += 1;
+        }
+    }
+
+    /// Count a double rotation done by `balance`.
+    #[inline]
+    pub(crate) fn double_rotation(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.double_rotations += 1;
+        }
+    }
+
+    /// Count a node compared by `find`.
+    #[inline]
+    pub(crate) fn visit(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.visits += 1;
+        }
+    }
+
+    /// Returns the number of rotations, a double rotation counts once.
+    #[cfg(feature = "stats")]
+    fn rebalances(&self) -> u64 {
+        self.single_rotations + self.double_rotations
+    }
+}
+
+/// The statistics of a tree. Atomic, so finds can count through a shared reference.
+#[derive(Default)]
+pub(crate) struct Recorder {
+    #[cfg(feature = "stats")]
+    inserts: AtomicU64,
+    #[cfg(feature = "stats")]
+    erases: AtomicU64,
+    #[cfg(feature = "stats")]
+    finds: AtomicU64,
+    #[cfg(feature = "stats")]
+    single_rotations: AtomicU64,
+    #[cfg(feature = "stats")]
+    double_rotations: AtomicU64,
+    #[cfg(feature = "stats")]
+    insert_rebalances: AtomicU64,
+    #[cfg(feature = "stats")]
+    max_insert_rebalances: AtomicU64,
+    #[cfg(feature = "stats")]
+    erase_rebalances: AtomicU64,
+    #[cfg(feature = "stats")]
+    max_erase_rebalances: AtomicU64,
+    #[cfg(feature = "stats")]
+    find_visits: AtomicU64,
+    #[cfg(feature = "stats")]
+    max_find_visits: AtomicU64,
+    #[cfg(feature = "stats")]
+    max_height: AtomicI32,
+}
+
+impl Clone for Recorder {
+    fn clone(&self) -> Recorder {
+        #[cfg(feature = "stats")]
+        {
+            let stats = self.snapshot();
+            Recorder {
+                inserts: AtomicU64::new(stats.inserts),
+                erases: AtomicU64::new(stats.erases),
+                finds: AtomicU64::new(stats.finds),
+                single_rotations: AtomicU64::new(stats.single_rotations),
+                double_rotations: AtomicU64::new(stats.double_rotations),
+                insert_rebalances: AtomicU64::new(stats.insert_rebalances),
+                max_insert_rebalances: AtomicU64::new(stats.max_insert_rebalances),
+                erase_rebalances: AtomicU64::new(stats.erase_rebalances),
+                max_erase_rebalances: AtomicU64::new(stats.max_erase_rebalances),
+                find_visits: AtomicU64::new(stats.find_visits),
+                max_find_visits: AtomicU64::new(stats.max_find_visits),
+                max_height: AtomicI32::new(stats.max_height),
+            }
+        }
+        #[cfg(not(feature = "stats"))]
+        Recorder {}
+    }
+}
+
+impl Recorder {
+    /// Add the counters of an insert.
+    ///
+    /// # Arguments
+    ///
+    /// * `counters` - Counters of the insert
+    /// * `height` - Height of the tree after the insert
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn record_insert(&self, counters: &OpCounters, height: i32) {
+        #[cfg(feature = "stats")]
+        {
+            self.inserts.fetch_add(1, Ordering::Relaxed);
+            self.record_rotations(counters);
+            self.insert_rebalances.fetch_add(counters.rebalances(), Ordering::Relaxed);
+            self.max_insert_rebalances.fetch_max(counters.rebalances(), Ordering::Relaxed);
+            self.max_height.fetch_max(height, Ordering::Relaxed);
+        }
+    }
+
+    /// Add the counters of an erase.
+    ///
+    /// # Argument
+    ///
+    /// * `counters` - Counters of the erase
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn record_erase(&self, counters: &OpCounters) {
+        #[cfg(feature = "stats")]
+        {
+            self.erases.fetch_add(1, Ordering::Relaxed);
+            self.record_rotations(counters);
+            self.erase_rebalances.fetch_add(counters.rebalances(), Ordering::Relaxed);
+            self.max_erase_rebalances.fetch_max(counters.rebalances(), Ordering::Relaxed);
+        }
+    }
+
+    /// Add the counters of a find.
+    ///
+    /// # Argument
+    ///
+    /// * `counters` - Counters of the find
+    #[inline]
+    #[allow(unused_variables)]
+    pub(crate) fn record_find(&self, counters: &OpCounters) {
+        #[cfg(feature = "stats")]
+        {
+            self.finds.fetch_add(1, Ordering::Relaxed);
+            self.find_visits.fetch_add(counters.visits, Ordering::Relaxed);
+            self.max_find_visits.fetch_max(counters.visits, Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(feature = "stats")]
+    fn record_rotations(&self, counters: &OpCounters) {
+        self.single_rotations.fetch_add(counters.single_rotations, Ordering::Relaxed);
+        self.double_rotations.fetch_add(counters.double_rotations, Ordering::Relaxed);
+    }
+
+    /// Returns the current values of the counters.
+    #[cfg(feature = "stats")]
+    fn snapshot(&self) -> Stats {
+        Stats {
+            inserts: self.inserts.load(Ordering::Relaxed),
+            erases: self.erases.load(Ordering::Relaxed),
+            finds: self.finds.load(Ordering::Relaxed),
+            single_rotations: self.single_rotations.load(Ordering::Relaxed),
+            double_rotations: self.double_rotations.load(Ordering::Relaxed),
+            insert_rebalances: self.insert_rebalances.load(Ordering::Relaxed),
+            max_insert_rebalances: self.max_insert_rebalances.load(Ordering::Relaxed),
+            erase_rebalances: self.erase_rebalances.load(Ordering::Relaxed),
+            max_erase_rebalances: self.max_erase_rebalances.load(Ordering::Relaxed),
+            find_visits: self.find_visits.load(Ordering::Relaxed),
+            max_find_visits: self.max_find_visits.load(Ordering::Relaxed),
+            max_height: self.max_height.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! Check of the invariants of an AVL tree.
+
+use std::cmp;
+use std::error::Error;
+use std::fmt;
+
+use super::{height, size, Node, Tree};
+
+/// An invariant of the tree which does not hold, with the data of the offending node
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvariantError {
+    /// The node is not greater than the node before it in sorted order
+    NotSorted { age: i32, name: String, previous_age: i32, previous_name: String },
+    /// The height stored in the node differs from the height of its subtree
+    WrongHeight { age: i32, name: String, stored: i32, actual: i32 },
+    /// The heights of the children of the node differ by more than one
+    Unbalanced { age: i32, name: String, balance: i32 },
+    /// The size stored in the node differs from the number of nodes in its subtree
+    WrongSize { age: i32, name: String, stored: usize, actual: usize },
+}
+
+impl fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantError::NotSorted { age, name, previous_age, previous_name } => {
+                write!(f, "node {{{}: {:?}}} is not greater than the node {{{}: {:?}}} before it",
+                       age, name, previous_age, previous_name)
+            }
+            InvariantError::WrongHeight { age, name, stored, actual } => {
+                write!(f, "node {{{}: {:?}}} has height {}, but its subtree has height {}", age, name, stored, actual)
+            }
+            InvariantError::Unbalanced { age, name, balance } => {
+                write!(f, "node {{{}: {:?}}} has balance factor {}", age, name, balance)
+            }
+            InvariantError::WrongSize { age, name, stored, actual } => {
+                write!(f, "node {{{}: {:?}}} has size {}, but its subtree has {} nodes", age, name, stored, actual)
+            }
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+impl Tree {
+    /// Check that the tree is a valid AVL tree.
+    /// Returns the first violated invariant which is found.
+    ///
+    /// # Discussion
+    ///
+    /// The following invariants are checked:
+    ///
+    /// * the data is strictly increasing in sorted order, by age first and by name second
+    /// * the height stored in every node is the height of its subtree
+    /// * the balance factor of every node is within [-1, 1]
+    /// * the size stored in every node is the number of nodes in its subtree
+    pub fn validate(&self) -> Result<(), InvariantError> {
+        let mut previous = None;
+        validate_node(&self.root, &mut previous).map(|_| ())
+    }
+}
+
+/// Check the invariants of the given subtree.
+/// Returns the actual height of the subtree.
+///
+/// # Arguments
+///
+/// * `node` - Root of the subtree which should be checked
+/// * `previous` - The node before this subtree in sorted order, updated to the last node of the subtree
+fn validate_node<'a>(node: &'a Option<Box<Node>>, previous: &mut Option<&'a Node>) -> Result<i32, InvariantError> {
+    let node = match node {
+        Some(node) => node,
+        None => return Ok(0)
+    };
+    let left = validate_node(&node.left, previous)?;
+    if let Some(previous) = previous {
+        if (previous.age, &previous.name) >= (node.age, &node.name) {
+            return Err(InvariantError::NotSorted {
+                age: node.age,
+                name: node.name.clone(),
+                previous_age: previous.age,
+                previous_name: previous.name.clone(),
+            });
+        }
+    }
+    *previous = Some(node);
+    let right = validate_node(&node.right, previous)?;
+
+    let actual = cmp::max(left, right) + 1;
+    if node.height != actual {
+        return Err(InvariantError::WrongHeight { age: node.age, name: node.name.clone(), stored: node.height, actual });
+    }
+    let balance = height(&node.left) - height(&node.right);
+    if !(-1..=1).contains(&balance) {
+        return Err(InvariantError::Unbalanced { age: node.age, name: node.name.clone(), balance });
+    }
+    // The sizes of the children were checked by the recursive calls
+    let actual_size = size(&node.left) + size(&node.right) + 1;
+    if node.size != actual_size {
+        return Err(InvariantError::WrongSize { age: node.age, name: node.name.clone(), stored: node.size, actual: actual_size });
+    }
+    Ok(actual)
+}
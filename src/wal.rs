@@ -0,0 +1,365 @@
+//! Write-ahead log which makes the tree of the command interpreter survive a crash.
+//!
+//! Every mutation is appended to the log and synced to disk before it is applied to the tree.
+//! On startup the tree is loaded from the snapshot (if any) and the log is replayed on top of it.
+//! Compaction writes a new snapshot and empties the log.
+//!
+//! The log starts with the magic bytes `AVLW` and a u16 version, followed by records.
+//! All integers are little endian. A record consists of:
+//!
+//! | Field     | Size         | Description                                           |
+//! |-----------|--------------|-------------------------------------------------------|
+//! | length    | u32          | Length of the payload                                 |
+//! | payload   | length bytes | Operation byte, for insert and erase: i32 age, name   |
+//! | checksum  | u32          | CRC-32 (IEEE) of the payload                          |
+//!
+//! A record at the end of the log which is cut off or fails its checksum marks the point where the
+//! process died while writing, it is discarded during recovery. A damaged record which is followed
+//! by valid records cannot come from a crash, so recovery fails then instead of losing them. As a
+//! damaged length hides where the next record starts, a valid record is searched at every offset
+//! after the damaged one, also when the length points past the end of the log.
+//!
+//! Replaying a log on top of a snapshot which already contains its mutations gives the same tree,
+//! so a crash between writing the snapshot and emptying the log is harmless.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::tree::{Crc32, Tree};
+
+/// Magic bytes at the start of every log
+pub const WAL_MAGIC: [u8; 4] = *b"AVLW";
+
+/// Version of the log format
+pub const WAL_VERSION: u16 = 1;
+
+/// Size of the magic bytes and version at the start of the log
+const HEADER_LEN: u64 = 6;
+
+/// Settings of the write-ahead log
+#[derive(Clone, Debug)]
+pub struct WalOptions {
+    /// Path of the log file, it is created if it does not exist
+    pub log_path: PathBuf,
+    /// Path of the snapshot the log is replayed on. Without it the log is never compacted.
+    pub snapshot_path: Option<PathBuf>,
+    /// Compact the log after this many records, 0 never compacts
+    pub compact_after: usize,
+}
+
+/// A mutation of the tree, as stored in the log
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mutation {
+    Insert{age: i32, name: String},
+    Erase{age: i32, name: String},
+    Reset,
+}
+
+/// Returns the mutations which turn any tree into one with the same entries as the given tree:
+/// a reset followed by an insert of every entry.
+///
+/// # Argument
+///
+/// * `tree` - The tree whose entries should be logged
+pub fn content_mutations(tree: &Tree) -> Vec<Mutation> {
+    let mut mutations = vec![Mutation::Reset];
+    mutations.extend(tree.iter().map(|(age, name)| Mutation::Insert{age, name: name.to_string()}));
+    mutations
+}
+
+impl Mutation {
+    /// Apply the mutation to the given tree.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree which should be changed
+    pub fn apply(&self, tree: &mut Tree) {
+        match self {
+            Mutation::Insert{age, name} => tree.insert(*age, name.clone()),
+            Mutation::Erase{age, name} => tree.erase(*age, name.clone()),
+            Mutation::Reset => {
+                tree.delete();
+                *tree = Tree::new();
+            }
+        }
+    }
+
+    /// Returns the payload of the log record of this mutation.
+    fn encode(&self) -> Vec<u8> {
+        let (op, data) = match self {
+            Mutation::Insert{age, name} => (b'i', Some((age, name))),
+            Mutation::Erase{age, name} => (b'e', Some((age, name))),
+            Mutation::Reset => (b'x', None),
+        };
+        let mut payload = vec![op];
+        if let Some((age, name)) = data {
+            payload.extend_from_slice(&age.to_le_bytes());
+            payload.extend_from_slice(name.as_bytes());
+        }
+        payload
+    }
+
+    /// Returns the mutation stored in the given payload, or None if the payload is invalid.
+    ///
+    /// # Argument
+    ///
+    /// * `payload` - Payload of a log record
+    fn decode(payload: &[u8]) -> Option<Mutation> {
+        match payload.split_first()? {
+            (b'x', []) => Some(Mutation::Reset),
+            (&op, rest) if (op == b'i' || op == b'e') && rest.len() >= 4 => {
+                let mut age = [0; 4];
+                age.copy_from_slice(&rest[..4]);
+                let age = i32::from_le_bytes(age);
+                let name = String::from_utf8(rest[4..].to_vec()).ok()?;
+                if op == b'i' {
+                    Some(Mutation::Insert{age, name})
+                } else {
+                    Some(Mutation::Erase{age, name})
+                }
+            }
+            _ => None
+        }
+    }
+}
+
+/// An open write-ahead log
+pub struct Wal {
+    /// The log file, positioned at its end
+    file: File,
+    /// Settings the log was opened with
+    options: WalOptions,
+    /// Number of records in the log
+    records: usize,
+    /// Number of bytes of an incomplete record which were cut off the end of the log when it was opened
+    discarded: u64,
+}
+
+impl Wal {
+    /// Open the log and recover the tree from the snapshot and the log.
+    /// Returns the open log and the recovered tree.
+    ///
+    /// # Argument
+    ///
+    /// * `options` - Settings of the log
+    ///
+    /// # Discussion
+    ///
+    /// An incomplete record at the end of the log is cut off, so new records follow the last complete one,
+    /// see `discarded`. A damaged record followed by valid ones is an `InvalidData` error.
+    pub fn open(options: &WalOptions) -> io::Result<(Wal, Tree)> {
+        let mut tree = match options.snapshot_path {
+            Some(ref path) if path.exists() => {
+                Tree::load_from(BufReader::new(File::open(path)?))
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?
+            }
+            _ => Tree::new()
+        };
+
+        let created = !options.log_path.exists();
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&options.log_path)?;
+        let mut records = 0;
+        // A log shorter than its header was being created when the process died
+        let valid_len = if file.metadata()?.len() < HEADER_LEN {
+            file.set_len(0)?;
+            file.write_all(&WAL_MAGIC)?;
+            file.write_all(&WAL_VERSION.to_le_bytes())?;
+            file.sync_all()?;
+            if created {
+                sync_parent(&options.log_path)?;
+            }
+            HEADER_LEN
+        } else {
+            let mut reader = BufReader::new(&mut file);
+            let mut header = [0; HEADER_LEN as usize];
+            reader.read_exact(&mut header)?;
+            if header[..4] != WAL_MAGIC || header[4..] != WAL_VERSION.to_le_bytes() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("{} is not a write-ahead log", options.log_path.display())));
+            }
+            let mut valid_len = HEADER_LEN;
+            while let Some((mutation, len)) = read_record(&mut reader)? {
+                mutation.apply(&mut tree);
+                valid_len += len;
+                records += 1;
+            }
+            valid_len
+        };
+
+        let discarded = file.metadata()?.len() - valid_len;
+        if discarded > 0 {
+            let mut rest = Vec::new();
+            file.seek(SeekFrom::Start(valid_len))?;
+            file.read_to_end(&mut rest)?;
+            if (1..rest.len()).any(|start| record_at(&rest[start..]).is_some()) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                                          format!("damaged record at offset {} of {} is followed by valid records",
+                                                  valid_len, options.log_path.display())));
+            }
+            file.set_len(valid_len)?;
+            file.sync_all()?;
+        }
+        file.seek(SeekFrom::Start(valid_len))?;
+
+        Ok((Wal { file, options: options.clone(), records, discarded }, tree))
+    }
+
+    /// Returns the number of bytes of an incomplete record which were cut off the end of the log
+    /// when it was opened, 0 if the log ended with a complete record.
+    pub fn discarded(&self) -> u64 {
+        self.discarded
+    }
+
+    /// Append mutations to the log and sync it to disk.
+    /// The mutations may only be applied once this returned successfully.
+    ///
+    /// # Argument
+    ///
+    /// * `mutations` - Mutations which are about to be applied, in order
+    pub fn append(&mut self, mutations: &[Mutation]) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        for mutation in mutations {
+            let payload = mutation.encode();
+            let mut crc = Crc32::new();
+            crc.update(&payload);
+            bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&payload);
+            bytes.extend_from_slice(&crc.finish().to_le_bytes());
+        }
+        self.file.write_all(&bytes)?;
+        self.file.sync_data()?;
+        self.records += mutations.len();
+        Ok(())
+    }
+
+    /// Compact the log if it holds enough records, see `WalOptions::compact_after`.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree with all logged mutations applied
+    pub fn maybe_compact(&mut self, tree: &Tree) -> io::Result<()> {
+        if self.options.compact_after > 0 && self.records >= self.options.compact_after {
+            self.compact(tree)?;
+        }
+        Ok(())
+    }
+
+    /// Write the tree to the snapshot and empty the log.
+    /// Does nothing if no snapshot path is configured.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree with all logged mutations applied
+    ///
+    /// # Discussion
+    ///
+    /// The snapshot is written to a temporary file first and then renamed, so the previous
+    /// snapshot stays intact if the process dies while writing. The log is only emptied once
+    /// the rename is synced to disk, otherwise a crash could lose both.
+    pub fn compact(&mut self, tree: &Tree) -> io::Result<()> {
+        let path = match self.options.snapshot_path {
+            Some(ref path) => path,
+            None => return Ok(())
+        };
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp = File::create(&temp_path)?;
+        tree.save_to(BufWriter::new(&temp))?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        sync_parent(path)?;
+
+        self.file.set_len(HEADER_LEN)?;
+        self.file.seek(SeekFrom::Start(HEADER_LEN))?;
+        self.file.sync_all()?;
+        self.records = 0;
+        Ok(())
+    }
+}
+
+/// Sync the directory which contains a file, which makes creating or renaming the file durable.
+///
+/// # Argument
+///
+/// * `path` - Path of the file
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new(".")
+    };
+    File::open(parent)?.sync_all()
+}
+
+/// Directories cannot be synced on other platforms, so this does nothing there.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Read the next record of the log.
+/// Returns the mutation and the size of the record, or None at the end of the log or at a record
+/// which is cut off, fails its checksum or holds no valid mutation.
+///
+/// # Argument
+///
+/// * `reader` - A reader positioned at the start of a record
+fn read_record<R: Read>(reader: &mut R) -> io::Result<Option<(Mutation, u64)>> {
+    let mut len = [0; 4];
+    if !read_full(reader, &mut len)? {
+        return Ok(None);
+    }
+    let len = u32::from_le_bytes(len);
+    let mut payload = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut payload)?;
+    let mut checksum = [0; 4];
+    if payload.len() != len as usize || !read_full(reader, &mut checksum)? {
+        return Ok(None);
+    }
+    Ok(decode_record(&payload, checksum).map(|mutation| (mutation, u64::from(len) + 8)))
+}
+
+/// Returns the mutation of a valid record at the start of the given bytes, or None if there is none.
+///
+/// # Argument
+///
+/// * `bytes` - Bytes of the log from the assumed start of a record
+fn record_at(bytes: &[u8]) -> Option<Mutation> {
+    let mut len = [0; 4];
+    len.copy_from_slice(bytes.get(..4)?);
+    let end = 4usize.checked_add(u32::from_le_bytes(len) as usize)?;
+    let mut checksum = [0; 4];
+    checksum.copy_from_slice(bytes.get(end..end.checked_add(4)?)?);
+    decode_record(&bytes[4..end], checksum)
+}
+
+/// Returns the mutation in the payload of a record, or None if the payload fails its checksum or is invalid.
+///
+/// # Arguments
+///
+/// * `payload` - Payload of the record
+/// * `checksum` - Checksum stored after the payload
+fn decode_record(payload: &[u8], checksum: [u8; 4]) -> Option<Mutation> {
+    let mut crc = Crc32::new();
+    crc.update(payload);
+    if crc.finish() != u32::from_le_bytes(checksum) {
+        return None;
+    }
+    Mutation::decode(payload)
+}
+
+/// Fill the buffer from the reader.
+/// Returns false if the reader ended before the buffer was full.
+///
+/// # Arguments
+///
+/// * `reader` - A reader from which the bytes should be read
+/// * `buf` - Buffer which should be filled
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error)
+    }
+}
@@ -0,0 +1,118 @@
+//! Seeded workload generators, shared by the tests and the benchmarks.
+//!
+//! The same seed always gives the same workload, so benchmark results and test failures can
+//! be reproduced. Keys returned by `sequential` and `random` have an even age, keys returned
+//! by `misses` an odd one, so a miss is never present in a tree built from the other two.
+
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::Tree;
+
+/// Length of the names of generated keys
+const NAME_LEN: usize = 8;
+
+/// An operation of a mixed workload
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    Insert(i32, String),
+    Erase(i32, String),
+    Find(i32, String),
+}
+
+impl Op {
+    /// Apply the operation to the given tree.
+    /// Returns the result of a find, false for the other operations.
+    ///
+    /// # Argument
+    ///
+    /// * `tree` - The tree the operation should be applied to
+    pub fn apply(&self, tree: &mut Tree) -> bool {
+        match self {
+            Op::Insert(age, name) => tree.insert(*age, name.clone()),
+            Op::Erase(age, name) => tree.erase(*age, name.clone()),
+            Op::Find(age, name) => return tree.find(*age, name.clone()),
+        }
+        false
+    }
+}
+
+/// Returns a random number generator for the given seed.
+///
+/// # Argument
+///
+/// * `seed` - Seed of the generator
+pub fn rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Returns `n` distinct keys in increasing order.
+///
+/// # Argument
+///
+/// * `n` - Number of keys, at most 2^30
+pub fn sequential(n: usize) -> Vec<(i32, String)> {
+    (0..n).map(|i| (2 * i as i32, format!("name{:04}", i % 10000))).collect()
+}
+
+/// Returns `n` distinct keys in random order.
+///
+/// # Arguments
+///
+/// * `n` - Number of keys
+/// * `seed` - Seed of the generator
+pub fn random(n: usize, seed: u64) -> Vec<(i32, String)> {
+    let mut rng = rng(seed);
+    let mut seen = HashSet::with_capacity(n);
+    let mut keys = Vec::with_capacity(n);
+    while keys.len() < n {
+        let key = (rng.gen::<i32>() & !1, random_name(&mut rng));
+        if seen.insert(key.clone()) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// Returns `n` keys in random order which are never returned by `sequential` or `random`.
+///
+/// # Arguments
+///
+/// * `n` - Number of keys
+/// * `seed` - Seed of the generator
+pub fn misses(n: usize, seed: u64) -> Vec<(i32, String)> {
+    let mut rng = rng(seed);
+    (0..n).map(|_| (rng.gen::<i32>() | 1, random_name(&mut rng))).collect()
+}
+
+/// Returns `len` operations on keys drawn uniformly from `keys`:
+/// half of them finds, a quarter inserts and a quarter erases.
+///
+/// # Arguments
+///
+/// * `keys` - The keys the operations work on
+/// * `len` - Number of operations
+/// * `seed` - Seed of the generator
+pub fn mixed(keys: &[(i32, String)], len: usize, seed: u64) -> Vec<Op> {
+    let mut rng = rng(seed);
+    (0..len).map(|_| {
+        let (age, name) = keys.choose(&mut rng).expect("mixed workload without keys").clone();
+        match rng.gen_range(0, 4) {
+            0 => Op::Insert(age, name),
+            1 => Op::Erase(age, name),
+            _ => Op::Find(age, name),
+        }
+    }).collect()
+}
+
+/// Returns a name of lowercase letters.
+///
+/// # Argument
+///
+/// * `rng` - The generator the letters are drawn from
+fn random_name(rng: &mut StdRng) -> String {
+    (0..NAME_LEN).map(|_| rng.gen_range(b'a', b'z' + 1) as char).collect()
+}
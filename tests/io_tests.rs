@@ -0,0 +1,203 @@
+//! Runs the scripts in `io-tests` through `command_loop`.
+//!
+//! * Every `input<X>.txt` must print `expected_output_for_input<X>.txt`. Run with `BLESS=1` to
+//!   write the actual outputs to the expected files instead, then review the changes with git.
+//! * Every `dump<X>.txt` must print only trees, in the JSON structure of `p`, which are sorted
+//!   and balanced.
+//!
+//! Add a test by adding files with these names, there is nothing to register.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rustsint::command_loop;
+use serde_json::Value;
+
+/// Returns the files in `io-tests` whose name has the given prefix and the `.txt` extension, sorted by name.
+///
+/// # Argument
+///
+/// * `prefix` - Start of the file names
+fn discover(prefix: &str) -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("io-tests");
+    let mut files: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|error| panic!("unable to read {}: {}", directory.display(), error))
+        .map(|entry| entry.expect("directory entry").path())
+        .filter(|path| {
+            let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            name.starts_with(prefix) && name.ends_with(".txt")
+        })
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no {}*.txt in {}", prefix, directory.display());
+    files
+}
+
+/// Returns what the commands in a file print.
+///
+/// # Argument
+///
+/// * `path` - The file with the commands
+fn run(path: &Path) -> String {
+    let input = fs::read(path).unwrap_or_else(|error| panic!("unable to read {}: {}", path.display(), error));
+    let mut output = Vec::new();
+    command_loop(&mut input.as_slice(), &mut output).expect("writing to a Vec does not fail");
+    String::from_utf8(output).unwrap_or_else(|_| panic!("{} printed invalid UTF-8", path.display()))
+}
+
+/// Returns the name of a file relative to the crate, for messages.
+fn name(path: &Path) -> String {
+    path.strip_prefix(env!("CARGO_MANIFEST_DIR")).unwrap_or(path).display().to_string()
+}
+
+/// Panic with every failure, if there are any.
+///
+/// # Argument
+///
+/// * `failures` - One message per failed file
+fn report(failures: Vec<String>) {
+    if !failures.is_empty() {
+        panic!("{} of the io-tests failed:\n\n{}", failures.len(), failures.join("\n"));
+    }
+}
+
+#[test]
+fn test_inputs_print_expected_output() {
+    let bless = env::var_os("BLESS").is_some_and(|bless| bless != "0" && !bless.is_empty());
+    let mut failures = Vec::new();
+    for input in discover("input") {
+        let file_name = input.file_name().and_then(|name| name.to_str()).expect("UTF-8 file name");
+        let expected_path = input.with_file_name(format!("expected_output_for_{}", file_name));
+        let actual = run(&input);
+        if bless {
+            fs::write(&expected_path, &actual)
+                .unwrap_or_else(|error| panic!("unable to write {}: {}", expected_path.display(), error));
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => {
+                failures.push(format!("{} does not print {}:\n{}", name(&input), name(&expected_path), diff(&expected, &actual)));
+            }
+            Err(error) => {
+                failures.push(format!("unable to read {}: {}, run with BLESS=1 to create it\n", name(&expected_path), error));
+            }
+        }
+    }
+    report(failures);
+}
+
+#[test]
+fn test_dumps_print_sorted_balanced_trees() {
+    let mut failures = Vec::new();
+    for dump in discover("dump") {
+        let output = run(&dump);
+        let mut trees = 0;
+        for (index, value) in serde_json::Deserializer::from_str(&output).into_iter::<Value>().enumerate() {
+            let problem = match value {
+                Ok(tree) => {
+                    trees += 1;
+                    check_tree(&tree)
+                }
+                Err(error) => Err(format!("is not JSON: {}", error)),
+            };
+            if let Err(problem) = problem {
+                failures.push(format!("{}: tree {} {}\n{}\n", name(&dump), index + 1, problem, output));
+                break;
+            }
+        }
+        if trees == 0 {
+            failures.push(format!("{}: prints no tree\n", name(&dump)));
+        }
+    }
+    report(failures);
+}
+
+/// Check that a printed tree has the structure of `p` and is sorted and balanced.
+/// Returns a description of the first problem which was found.
+///
+/// # Argument
+///
+/// * `tree` - The parsed output of `p`
+fn check_tree(tree: &Value) -> Result<(), String> {
+    let mut entries = Vec::new();
+    check_node(tree, &mut entries)?;
+    match entries.windows(2).find(|pair| pair[0] >= pair[1]) {
+        Some(pair) => Err(format!("is not sorted: {:?} comes before {:?}", pair[0], pair[1])),
+        None => Ok(())
+    }
+}
+
+/// Check the structure and balance of a subtree and collect its entries in order.
+/// Returns the height of the subtree, or a description of the first problem which was found.
+///
+/// # Arguments
+///
+/// * `node` - `null` or `[{"<age>": "<name>"}, <left>, <right>]`
+/// * `entries` - The entries before the subtree, its entries are appended
+fn check_node(node: &Value, entries: &mut Vec<(i32, String)>) -> Result<usize, String> {
+    let (data, left, right) = match node {
+        Value::Null => return Ok(0),
+        Value::Array(items) if items.len() == 3 => (&items[0], &items[1], &items[2]),
+        _ => return Err(format!("has an invalid node {}", node)),
+    };
+    let entry = match data.as_object().filter(|data| data.len() == 1).and_then(|data| data.iter().next()) {
+        Some((age, Value::String(name))) => match age.parse() {
+            Ok(age) => (age, name.clone()),
+            Err(_) => return Err(format!("has an invalid age in {}", data)),
+        },
+        _ => return Err(format!("has invalid data {}", data)),
+    };
+    let left_height = check_node(left, entries)?;
+    entries.push(entry);
+    let right_height = check_node(right, entries)?;
+    if left_height.abs_diff(right_height) > 1 {
+        return Err(format!("is not balanced at {}: the subtrees have heights {} and {}", data, left_height, right_height));
+    }
+    Ok(left_height.max(right_height) + 1)
+}
+
+/// Returns the differences between the expected and the actual output, line by line.
+/// Lines only in the expected output start with `-`, lines only in the actual output with `+`,
+/// both with the line number in their file.
+///
+/// # Arguments
+///
+/// * `expected` - The expected output
+/// * `actual` - The actual output
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    // common[i][j] is the length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut common = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && common[i + 1][j] >= common[i][j + 1]) {
+            let _ = writeln!(out, "  -{:>4} {}", i + 1, expected[i]);
+            i += 1;
+        } else {
+            let _ = writeln!(out, "  +{:>4} {}", j + 1, actual[j]);
+            j += 1;
+        }
+    }
+    if out.is_empty() {
+        // Only the line endings differ
+        out.push_str("  the outputs differ in their line endings\n");
+    }
+    out
+}
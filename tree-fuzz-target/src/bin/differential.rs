@@ -0,0 +1,10 @@
+#[macro_use]
+extern crate afl;
+
+fn main() {
+    fuzz!(|data: &[u8]| {
+        if let Err(error) = rustsint::differential::run_bytes(data) {
+            panic!("{}", error);
+        }
+    });
+}
@@ -1,12 +1,18 @@
 #[macro_use]
 extern crate afl;
 
-#[path = "../../src/main.rs"] mod main;
+use std::io;
+
+use rustsint::{command_loop_with, Options};
 
 fn main() {
+    // The fuzzer must not write or read arbitrary files with save and load
+    let options = Options { validate: true, files: false, ..Options::default() };
     fuzz!(|data: &[u8]| {
-        let mut mut_data = data.clone();
-        main::command_loop(&mut mut_data);
+        let mut mut_data = data;
+        // Without a write-ahead log the only possible error is a broken tree invariant
+        if let Err(error) = command_loop_with(&mut mut_data, &mut io::sink(), &options) {
+            panic!("{}", error);
+        }
     });
 }
-